@@ -0,0 +1,633 @@
+use std::{fs, io, path::PathBuf};
+
+use bevy::{
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+use bevy_sokoban::{storage::data_directory, Direction};
+
+use crate::gamepad_plugin::{read_gamepads, GamepadInput, BUTTONS};
+
+pub struct ActionPlugin;
+
+// What the player can do, whatever key or button they do it with.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Action {
+    MoveUp,
+    MoveDown,
+    MoveLeft,
+    MoveRight,
+    Undo,
+    Redo,
+    Restart,
+    Pause,
+    ToggleEditor,
+    Hint,
+    Replay,
+    LevelSelect,
+    ExportSolution,
+    PlaceFloor,
+    PlaceBlock,
+    PlaceGoal,
+    PlacePlayer,
+    Remove,
+    Playtest,
+    SaveLevel,
+    OpenLevel,
+    CheckLevel,
+    GenerateLevel,
+    Settings,
+    Confirm,
+    Cancel,
+}
+
+impl Action {
+    pub const ALL: [Action; 26] = [
+        Action::MoveUp,
+        Action::MoveDown,
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::Undo,
+        Action::Redo,
+        Action::Restart,
+        Action::Pause,
+        Action::ToggleEditor,
+        Action::Hint,
+        Action::Replay,
+        Action::LevelSelect,
+        Action::ExportSolution,
+        Action::PlaceFloor,
+        Action::PlaceBlock,
+        Action::PlaceGoal,
+        Action::PlacePlayer,
+        Action::Remove,
+        Action::Playtest,
+        Action::SaveLevel,
+        Action::OpenLevel,
+        Action::CheckLevel,
+        Action::GenerateLevel,
+        Action::Settings,
+        Action::Confirm,
+        Action::Cancel,
+    ];
+
+    pub fn moving(direction: Direction) -> Action {
+        match direction {
+            Direction::Up => Action::MoveUp,
+            Direction::Down => Action::MoveDown,
+            Direction::Left => Action::MoveLeft,
+            Direction::Right => Action::MoveRight,
+        }
+    }
+
+    fn direction(self) -> Option<Direction> {
+        Direction::ALL
+            .into_iter()
+            .find(|direction| Action::moving(*direction) == self)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::MoveUp => "move-up",
+            Action::MoveDown => "move-down",
+            Action::MoveLeft => "move-left",
+            Action::MoveRight => "move-right",
+            Action::Undo => "undo",
+            Action::Redo => "redo",
+            Action::Restart => "restart",
+            Action::Pause => "pause",
+            Action::ToggleEditor => "editor",
+            Action::Hint => "hint",
+            Action::Replay => "replay",
+            Action::LevelSelect => "level-select",
+            Action::ExportSolution => "export-solution",
+            Action::PlaceFloor => "place-floor",
+            Action::PlaceBlock => "place-block",
+            Action::PlaceGoal => "place-goal",
+            Action::PlacePlayer => "place-player",
+            Action::Remove => "remove",
+            Action::Playtest => "playtest",
+            Action::SaveLevel => "save-level",
+            Action::OpenLevel => "open-level",
+            Action::CheckLevel => "check-level",
+            Action::GenerateLevel => "generate-level",
+            Action::Settings => "settings",
+            Action::Confirm => "confirm",
+            Action::Cancel => "cancel",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Action::MoveUp => "Move up",
+            Action::MoveDown => "Move down",
+            Action::MoveLeft => "Move left",
+            Action::MoveRight => "Move right",
+            Action::Undo => "Undo",
+            Action::Redo => "Redo",
+            Action::Restart => "Restart level",
+            Action::Pause => "Pause",
+            Action::ToggleEditor => "Open the editor",
+            Action::Hint => "Hint",
+            Action::Replay => "Replay solution",
+            Action::LevelSelect => "Level select",
+            Action::ExportSolution => "Export solution",
+            Action::PlaceFloor => "Editor: floor",
+            Action::PlaceBlock => "Editor: block",
+            Action::PlaceGoal => "Editor: goal",
+            Action::PlacePlayer => "Editor: player",
+            Action::Remove => "Editor: remove",
+            Action::Playtest => "Editor: playtest",
+            Action::SaveLevel => "Editor: save",
+            Action::OpenLevel => "Editor: open",
+            Action::CheckLevel => "Editor: check",
+            Action::GenerateLevel => "Editor: generate",
+            Action::Settings => "Controls",
+            Action::Confirm => "Choose or confirm",
+            Action::Cancel => "Cancel",
+        }
+    }
+
+    fn default_bindings(self) -> Vec<Binding> {
+        use Binding::{Button, Key};
+        use GamepadButtonType as B;
+
+        // Playing takes the shoulder buttons and the editor the face buttons, so they don't overlap.
+        match self {
+            Action::MoveUp => vec![Key(KeyCode::Up), Button(B::DPadUp)],
+            Action::MoveDown => vec![Key(KeyCode::Down), Button(B::DPadDown)],
+            Action::MoveLeft => vec![Key(KeyCode::Left), Button(B::DPadLeft)],
+            Action::MoveRight => vec![Key(KeyCode::Right), Button(B::DPadRight)],
+            Action::Undo => vec![Key(KeyCode::U), Button(B::LeftTrigger)],
+            Action::Redo => vec![Key(KeyCode::R), Button(B::RightTrigger)],
+            Action::Restart => vec![Key(KeyCode::Back), Button(B::LeftTrigger2)],
+            Action::Pause => vec![Key(KeyCode::Space), Button(B::Start)],
+            Action::ToggleEditor => vec![Key(KeyCode::E)],
+            Action::Hint => vec![Key(KeyCode::H)],
+            Action::Replay => vec![Key(KeyCode::P)],
+            Action::LevelSelect => vec![Key(KeyCode::M), Button(B::Select)],
+            Action::ExportSolution => vec![Key(KeyCode::X)],
+            Action::PlaceFloor => vec![Key(KeyCode::Z), Button(B::South)],
+            Action::PlaceBlock => vec![Key(KeyCode::X), Button(B::West)],
+            Action::PlaceGoal => vec![Key(KeyCode::C), Button(B::North)],
+            Action::PlacePlayer => vec![Key(KeyCode::V), Button(B::East)],
+            Action::Remove => vec![Key(KeyCode::S), Button(B::RightTrigger2)],
+            Action::Playtest => vec![Key(KeyCode::P), Button(B::Start)],
+            Action::SaveLevel => vec![Key(KeyCode::E)],
+            Action::OpenLevel => vec![Key(KeyCode::L)],
+            Action::CheckLevel => vec![Key(KeyCode::K)],
+            Action::GenerateLevel => vec![Key(KeyCode::G)],
+            Action::Settings => vec![Key(KeyCode::S)],
+            Action::Confirm => vec![Key(KeyCode::Return), Button(B::South)],
+            Action::Cancel => vec![Key(KeyCode::Delete), Button(B::East)],
+        }
+    }
+}
+
+// The keys that can be bound. Escape quits, or cancels picking a key, so it isn't one of them.
+pub const KEYS: [KeyCode; 77] = [
+    KeyCode::A,
+    KeyCode::B,
+    KeyCode::C,
+    KeyCode::D,
+    KeyCode::E,
+    KeyCode::F,
+    KeyCode::G,
+    KeyCode::H,
+    KeyCode::I,
+    KeyCode::J,
+    KeyCode::K,
+    KeyCode::L,
+    KeyCode::M,
+    KeyCode::N,
+    KeyCode::O,
+    KeyCode::P,
+    KeyCode::Q,
+    KeyCode::R,
+    KeyCode::S,
+    KeyCode::T,
+    KeyCode::U,
+    KeyCode::V,
+    KeyCode::W,
+    KeyCode::X,
+    KeyCode::Y,
+    KeyCode::Z,
+    KeyCode::Key0,
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+    KeyCode::Key7,
+    KeyCode::Key8,
+    KeyCode::Key9,
+    KeyCode::Up,
+    KeyCode::Down,
+    KeyCode::Left,
+    KeyCode::Right,
+    KeyCode::Space,
+    KeyCode::Return,
+    KeyCode::Back,
+    KeyCode::Tab,
+    KeyCode::Insert,
+    KeyCode::Delete,
+    KeyCode::Home,
+    KeyCode::End,
+    KeyCode::PageUp,
+    KeyCode::PageDown,
+    KeyCode::Comma,
+    KeyCode::Period,
+    KeyCode::Slash,
+    KeyCode::Semicolon,
+    KeyCode::Apostrophe,
+    KeyCode::Minus,
+    KeyCode::Equals,
+    KeyCode::BracketLeft,
+    KeyCode::BracketRight,
+    KeyCode::Backslash,
+    KeyCode::Grave,
+    KeyCode::ShiftLeft,
+    KeyCode::ShiftRight,
+    KeyCode::ControlLeft,
+    KeyCode::ControlRight,
+    KeyCode::AltLeft,
+    KeyCode::AltRight,
+    KeyCode::Numpad0,
+    KeyCode::Numpad1,
+    KeyCode::Numpad2,
+    KeyCode::Numpad3,
+    KeyCode::Numpad4,
+    KeyCode::Numpad5,
+    KeyCode::Numpad6,
+    KeyCode::Numpad7,
+    KeyCode::Numpad8,
+    KeyCode::Numpad9,
+];
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Binding {
+    Key(KeyCode),
+    Button(GamepadButtonType),
+}
+
+impl Binding {
+    fn to_config(self) -> String {
+        match self {
+            Binding::Key(key) => format!("key:{:?}", key),
+            Binding::Button(button) => format!("button:{:?}", button),
+        }
+    }
+
+    fn from_config(text: &str) -> Option<Binding> {
+        if let Some(name) = text.strip_prefix("key:") {
+            KEYS.into_iter()
+                .find(|key| format!("{:?}", key) == name)
+                .map(Binding::Key)
+        } else if let Some(name) = text.strip_prefix("button:") {
+            BUTTONS
+                .into_iter()
+                .find(|button| format!("{:?}", button) == name)
+                .map(Binding::Button)
+        } else {
+            None
+        }
+    }
+
+    pub fn label(self) -> String {
+        match self {
+            Binding::Key(KeyCode::Back) => "Backspace".to_string(),
+            Binding::Key(KeyCode::Return) => "Enter".to_string(),
+            Binding::Key(key) => format!("{:?}", key),
+            Binding::Button(button) => format!("Pad {:?}", button),
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct Bindings(HashMap<Action, Vec<Binding>>);
+
+impl Default for Bindings {
+    fn default() -> Self {
+        Bindings(
+            Action::ALL
+                .into_iter()
+                .map(|action| (action, action.default_bindings()))
+                .collect(),
+        )
+    }
+}
+
+impl Bindings {
+    fn path() -> PathBuf {
+        data_directory().join("bindings.txt")
+    }
+
+    pub fn load() -> Bindings {
+        let text = fs::read_to_string(Bindings::path()).unwrap_or_default();
+        Bindings::parse(&text)
+    }
+
+    // Actions missing from the text keep their defaults.
+    fn parse(text: &str) -> Bindings {
+        let mut bindings = Bindings::default();
+        for line in text.lines() {
+            let mut fields = line.split('\t');
+            let Some(name) = fields.next() else {
+                continue;
+            };
+            let Some(action) = Action::ALL.into_iter().find(|action| action.name() == name) else {
+                warn!("Unknown action in the bindings file: {}", name);
+                continue;
+            };
+            bindings.clear(action);
+            for field in fields {
+                match Binding::from_config(field) {
+                    Some(binding) => bindings.add(action, binding),
+                    None => warn!("Unknown binding for {}: {}", name, field),
+                }
+            }
+        }
+        bindings
+    }
+
+    fn to_text(&self) -> String {
+        let mut text = String::new();
+        for action in Action::ALL {
+            text.push_str(action.name());
+            for binding in self.get(action) {
+                text.push('\t');
+                text.push_str(&binding.to_config());
+            }
+            text.push('\n');
+        }
+        text
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        fs::create_dir_all(data_directory())?;
+        fs::write(Bindings::path(), self.to_text())
+    }
+
+    pub fn get(&self, action: Action) -> &[Binding] {
+        self.0
+            .get(&action)
+            .map_or(&[], |bindings| bindings.as_slice())
+    }
+
+    pub fn add(&mut self, action: Action, binding: Binding) {
+        let bindings = self.0.entry(action).or_default();
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+    }
+
+    pub fn clear(&mut self, action: Action) {
+        self.0.insert(action, Vec::new());
+    }
+
+    pub fn key_label(&self, action: Action) -> String {
+        let bindings = self.get(action);
+        bindings
+            .iter()
+            .find(|binding| matches!(binding, Binding::Key(_)))
+            .or(bindings.first())
+            .map_or_else(|| "(unbound)".to_string(), |binding| binding.label())
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct Actions {
+    pressed: HashSet<Action>,
+    just_pressed: HashSet<Action>,
+}
+
+impl Actions {
+    pub fn pressed(&self, action: Action) -> bool {
+        self.pressed.contains(&action)
+    }
+
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.just_pressed.contains(&action)
+    }
+
+    pub fn reset(&mut self, action: Action) {
+        self.pressed.remove(&action);
+        self.just_pressed.remove(&action);
+    }
+
+    pub fn direction(&self) -> Option<Direction> {
+        Direction::ALL
+            .into_iter()
+            .find(|direction| self.pressed(Action::moving(*direction)))
+    }
+
+    pub fn just_pressed_direction(&self) -> Option<Direction> {
+        Direction::ALL
+            .into_iter()
+            .find(|direction| self.just_pressed(Action::moving(*direction)))
+    }
+}
+
+pub const MAX_BUFFER_DEPTH: usize = 8;
+
+// Moves pressed while the player is still moving, kept to play afterwards.
+#[derive(Resource)]
+pub struct BufferSettings {
+    pub depth: usize,
+    pub clear_on_undo: bool,
+}
+
+impl Default for BufferSettings {
+    fn default() -> Self {
+        BufferSettings {
+            depth: 2,
+            clear_on_undo: true,
+        }
+    }
+}
+
+impl BufferSettings {
+    fn path() -> PathBuf {
+        data_directory().join("input.txt")
+    }
+
+    pub fn load() -> BufferSettings {
+        let text = fs::read_to_string(BufferSettings::path()).unwrap_or_default();
+        BufferSettings::parse(&text)
+    }
+
+    fn parse(text: &str) -> BufferSettings {
+        let mut settings = BufferSettings::default();
+        for line in text.lines() {
+            let Some((name, value)) = line.split_once('\t') else {
+                continue;
+            };
+            match name {
+                "buffer-depth" => match value.parse::<usize>() {
+                    Ok(depth) => settings.depth = depth.min(MAX_BUFFER_DEPTH),
+                    Err(_) => warn!("Invalid buffer depth: {}", value),
+                },
+                "clear-buffer-on-undo" => match value.parse() {
+                    Ok(clear_on_undo) => settings.clear_on_undo = clear_on_undo,
+                    Err(_) => warn!("Invalid clear-buffer-on-undo value: {}", value),
+                },
+                _ => warn!("Unknown setting in the input file: {}", name),
+            }
+        }
+        settings
+    }
+
+    fn to_text(&self) -> String {
+        format!(
+            "buffer-depth\t{}\nclear-buffer-on-undo\t{}\n",
+            self.depth, self.clear_on_undo
+        )
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        fs::create_dir_all(data_directory())?;
+        fs::write(BufferSettings::path(), self.to_text())
+    }
+}
+
+fn update_actions(
+    keyboard_input: Res<Input<KeyCode>>,
+    gamepad_input: Res<GamepadInput>,
+    bindings: Res<Bindings>,
+    mut actions: ResMut<Actions>,
+) {
+    actions.pressed.clear();
+    actions.just_pressed.clear();
+
+    for action in Action::ALL {
+        let mut pressed = false;
+        let mut just_pressed = false;
+        for binding in bindings.get(action) {
+            match *binding {
+                Binding::Key(key) => {
+                    pressed |= keyboard_input.pressed(key);
+                    just_pressed |= keyboard_input.just_pressed(key);
+                }
+                Binding::Button(button) => {
+                    pressed |= gamepad_input.buttons.pressed(button);
+                    just_pressed |= gamepad_input.buttons.just_pressed(button);
+                }
+            }
+        }
+        if action.direction().is_some() && action.direction() == gamepad_input.stick {
+            pressed = true;
+            just_pressed |= gamepad_input.stick_just_moved;
+        }
+
+        if pressed {
+            actions.pressed.insert(action);
+        }
+        if just_pressed {
+            actions.just_pressed.insert(action);
+        }
+    }
+}
+
+fn load_bindings(mut commands: Commands) {
+    commands.insert_resource(Bindings::load());
+    commands.insert_resource(BufferSettings::load());
+}
+
+impl Plugin for ActionPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Bindings::default())
+            .insert_resource(BufferSettings::default())
+            .insert_resource(Actions::default())
+            .add_systems(Startup, load_bindings)
+            .add_systems(PreUpdate, update_actions.after(read_gamepads));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bindings_round_trip_through_text() {
+        let mut bindings = Bindings::default();
+        bindings.clear(Action::Undo);
+        bindings.add(Action::Undo, Binding::Key(KeyCode::Z));
+        bindings.add(
+            Action::Undo,
+            Binding::Button(GamepadButtonType::LeftTrigger),
+        );
+        bindings.clear(Action::Hint);
+
+        let parsed = Bindings::parse(&bindings.to_text());
+        for action in Action::ALL {
+            assert_eq!(parsed.get(action), bindings.get(action));
+        }
+    }
+
+    #[test]
+    fn skips_unknown_actions_and_bad_bindings() {
+        let parsed = Bindings::parse("jump\tkey:J\nundo\tkey:Z\tkey:Escape\tbutton:Nope\tZ\n");
+        assert_eq!(parsed.get(Action::Undo), [Binding::Key(KeyCode::Z)]);
+        assert_eq!(parsed.get(Action::Redo), Action::Redo.default_bindings());
+    }
+
+    #[test]
+    fn drops_duplicate_bindings() {
+        let parsed = Bindings::parse("undo\tkey:U\tbutton:East\tkey:U\n");
+        assert_eq!(
+            parsed.get(Action::Undo),
+            [
+                Binding::Key(KeyCode::U),
+                Binding::Button(GamepadButtonType::East)
+            ]
+        );
+    }
+
+    #[test]
+    fn play_and_editor_default_buttons_differ() {
+        let buttons = |actions: &[Action]| -> Vec<Binding> {
+            actions
+                .iter()
+                .flat_map(|action| action.default_bindings())
+                .filter(|binding| matches!(binding, Binding::Button(_)))
+                .collect()
+        };
+        let play = buttons(&[Action::Undo, Action::Redo, Action::Restart]);
+        let editor = buttons(&[
+            Action::PlaceFloor,
+            Action::PlaceBlock,
+            Action::PlaceGoal,
+            Action::PlacePlayer,
+            Action::Remove,
+        ]);
+        assert!(play.iter().all(|binding| !editor.contains(binding)));
+    }
+
+    #[test]
+    fn parses_buffer_settings() {
+        let settings = BufferSettings::parse("buffer-depth\t100\nclear-buffer-on-undo\tfalse\n");
+        assert_eq!(
+            (settings.depth, settings.clear_on_undo),
+            (MAX_BUFFER_DEPTH, false)
+        );
+
+        let settings = BufferSettings::parse(
+            "buffer-depth\tmany\nclear-buffer-on-undo\tmaybe\nspeed\t3\nbuffer-depth\n",
+        );
+        let defaults = BufferSettings::default();
+        assert_eq!(
+            (settings.depth, settings.clear_on_undo),
+            (defaults.depth, defaults.clear_on_undo)
+        );
+
+        let settings = BufferSettings::parse(
+            &BufferSettings {
+                depth: 5,
+                clear_on_undo: false,
+            }
+            .to_text(),
+        );
+        assert_eq!((settings.depth, settings.clear_on_undo), (5, false));
+    }
+}
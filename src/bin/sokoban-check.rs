@@ -0,0 +1,346 @@
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+    process::ExitCode,
+    time::Duration,
+};
+
+use bevy_sokoban::{
+    check::{check_layout, Severity},
+    difficulty::estimate_difficulty,
+    levels::{collection_to_xsb, parse_level_by_level, Collection, LevelEntry, LevelError},
+    solver::{Puzzle, SolveError, SolverConfig},
+};
+
+const USAGE: &str =
+    "usage: sokoban-check [--solve] [--time-limit SECONDS] [--strict] [--sort OUTPUT] FILE...
+
+Checks every level in the given XSB files and reports its problems.
+  --solve               also check that each level can be solved and rate its difficulty
+  --time-limit SECONDS  how long the solver may spend on one level (default 10)
+  --strict              fail on warnings as well as errors
+  --sort OUTPUT         write the levels of FILE to OUTPUT ordered from easy to hard, with levels
+                        that can't be rated last; needs exactly one FILE and implies --solve
+
+Exits with 0 when every level passes, 1 when a level has a problem, 2 when a file can't be read
+or the arguments are wrong, and 3 when the solver ran out of time without finding a problem.";
+
+const EXIT_PROBLEMS: u8 = 1;
+const EXIT_USAGE: u8 = 2;
+const EXIT_INCONCLUSIVE: u8 = 3;
+
+struct Options {
+    solve: bool,
+    time_limit: Duration,
+    strict: bool,
+    sort: Option<PathBuf>,
+    paths: Vec<PathBuf>,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut options = Options {
+        solve: false,
+        time_limit: Duration::from_secs(10),
+        strict: false,
+        sort: None,
+        paths: Vec::new(),
+    };
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--solve" => options.solve = true,
+            "--strict" => options.strict = true,
+            "--time-limit" => {
+                let seconds = args
+                    .next()
+                    .and_then(|value| value.parse::<f64>().ok())
+                    .filter(|seconds| *seconds > 0.0)
+                    .ok_or("--time-limit needs a positive number of seconds")?;
+                options.time_limit = Duration::from_secs_f64(seconds);
+            }
+            "--sort" => {
+                options.sort = Some(PathBuf::from(
+                    args.next().ok_or("--sort needs an output file")?,
+                ));
+                options.solve = true;
+            }
+            _ if arg.starts_with('-') => return Err(format!("unknown option {}", arg)),
+            _ => options.paths.push(PathBuf::from(arg)),
+        }
+    }
+
+    if options.paths.is_empty() {
+        return Err("no level files given".to_string());
+    }
+    if options.sort.is_some() && options.paths.len() != 1 {
+        return Err("--sort needs exactly one level file".to_string());
+    }
+    Ok(options)
+}
+
+#[derive(Default)]
+struct Summary {
+    levels: usize,
+    errors: usize,
+    warnings: usize,
+    inconclusive: usize,
+    io_errors: usize,
+}
+
+impl Summary {
+    fn exit_code(&self, strict: bool) -> ExitCode {
+        if self.io_errors > 0 {
+            ExitCode::from(EXIT_USAGE)
+        } else if self.errors > 0 || (strict && self.warnings > 0) {
+            ExitCode::from(EXIT_PROBLEMS)
+        } else if self.inconclusive > 0 {
+            ExitCode::from(EXIT_INCONCLUSIVE)
+        } else {
+            ExitCode::SUCCESS
+        }
+    }
+}
+
+fn check_level(
+    path: &Path,
+    name: &str,
+    level: &LevelEntry,
+    options: &Options,
+    summary: &mut Summary,
+) -> Option<f32> {
+    let problems = check_layout(&level.layout);
+    for problem in &problems {
+        println!(
+            "{}: {}: {}: {}",
+            path.display(),
+            name,
+            problem.severity,
+            problem.message
+        );
+        match problem.severity {
+            Severity::Error => summary.errors += 1,
+            Severity::Warning => summary.warnings += 1,
+        }
+    }
+
+    let has_errors = problems
+        .iter()
+        .any(|problem| problem.severity == Severity::Error);
+    if !options.solve || has_errors {
+        return None;
+    }
+    let puzzle = Puzzle::from_layout(&level.layout)?;
+    let config = SolverConfig {
+        time_limit: Some(options.time_limit),
+        ..SolverConfig::default()
+    };
+    match estimate_difficulty(&puzzle, &config) {
+        Ok(difficulty) => {
+            println!(
+                "{}: {}: solvable in {} moves and {} pushes, difficulty {:.1}",
+                path.display(),
+                name,
+                difficulty.moves,
+                difficulty.pushes,
+                difficulty.score
+            );
+            Some(difficulty.score)
+        }
+        Err(SolveError::Unsolvable) => {
+            println!("{}: {}: error: level has no solution", path.display(), name);
+            summary.errors += 1;
+            None
+        }
+        Err(error @ SolveError::BudgetExceeded { .. }) => {
+            println!("{}: {}: unknown: solver {}", path.display(), name, error);
+            summary.inconclusive += 1;
+            None
+        }
+    }
+}
+
+// Without a complete collection there is nothing to sort.
+fn check_file(
+    path: &Path,
+    options: &Options,
+    summary: &mut Summary,
+) -> Option<(Collection, Vec<Option<f32>>)> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(error) => {
+            eprintln!("{}: {}", path.display(), error);
+            summary.io_errors += 1;
+            return None;
+        }
+    };
+    let pack = parse_level_by_level(&text);
+    if pack.levels.is_empty() {
+        println!("{}: error: {}", path.display(), LevelError::Empty);
+        summary.errors += 1;
+        return None;
+    }
+
+    let mut levels = Vec::new();
+    let mut scores = Vec::new();
+    let mut is_complete = true;
+    for (index, level) in pack.levels.into_iter().enumerate() {
+        summary.levels += 1;
+        let level = match level {
+            Ok(level) => level,
+            Err(error) => {
+                println!("{}: level {}: error: {}", path.display(), index + 1, error);
+                summary.errors += 1;
+                is_complete = false;
+                continue;
+            }
+        };
+        let name = match &level.title {
+            Some(title) => format!("level {} ({})", index + 1, title),
+            None => format!("level {}", index + 1),
+        };
+        scores.push(check_level(path, &name, &level, options, summary));
+        levels.push(level);
+    }
+
+    if !is_complete {
+        return None;
+    }
+    let collection = Collection {
+        title: pack.title,
+        author: pack.author,
+        comments: pack.comments,
+        levels,
+    };
+    Some((collection, scores))
+}
+
+fn write_sorted(
+    output: &Path,
+    mut collection: Collection,
+    scores: Vec<Option<f32>>,
+) -> io::Result<()> {
+    let mut rated: Vec<(f32, LevelEntry)> = scores
+        .into_iter()
+        .map(|score| score.unwrap_or(f32::INFINITY))
+        .zip(collection.levels)
+        .collect();
+    rated.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+    collection.levels = rated.into_iter().map(|(_, level)| level).collect();
+    fs::write(output, collection_to_xsb(&collection))
+}
+
+fn main() -> ExitCode {
+    if env::args().any(|arg| arg == "-h" || arg == "--help") {
+        println!("{}", USAGE);
+        return ExitCode::SUCCESS;
+    }
+    let options = match parse_args(env::args().skip(1)) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("sokoban-check: {}\n\n{}", message, USAGE);
+            return ExitCode::from(EXIT_USAGE);
+        }
+    };
+
+    let mut summary = Summary::default();
+    for path in &options.paths {
+        let checked = check_file(path, &options, &mut summary);
+        let Some(output) = &options.sort else {
+            continue;
+        };
+        let Some((collection, scores)) = checked else {
+            println!(
+                "Not writing {} until every level can be read",
+                output.display()
+            );
+            continue;
+        };
+        match write_sorted(output, collection, scores) {
+            Ok(()) => println!("Wrote the sorted levels to {}", output.display()),
+            Err(error) => {
+                eprintln!("{}: {}", output.display(), error);
+                summary.io_errors += 1;
+            }
+        }
+    }
+
+    println!(
+        "{} levels checked: {} errors, {} warnings",
+        summary.levels, summary.errors, summary.warnings
+    );
+    if summary.inconclusive > 0 {
+        println!(
+            "{} levels could not be solved within the time limit",
+            summary.inconclusive
+        );
+    }
+    summary.exit_code(options.strict)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Options, String> {
+        parse_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn parses_options_and_files() {
+        let options = parse(&["--strict", "--time-limit", "2.5", "a.xsb", "b.xsb"]).unwrap();
+        assert!(options.strict);
+        assert!(!options.solve);
+        assert_eq!(options.time_limit, Duration::from_secs_f64(2.5));
+        assert_eq!(options.sort, None);
+        assert_eq!(
+            options.paths,
+            [PathBuf::from("a.xsb"), PathBuf::from("b.xsb")]
+        );
+    }
+
+    #[test]
+    fn sorting_implies_solving() {
+        let options = parse(&["--sort", "sorted.xsb", "a.xsb"]).unwrap();
+        assert!(options.solve);
+        assert_eq!(options.sort, Some(PathBuf::from("sorted.xsb")));
+    }
+
+    #[test]
+    fn rejects_bad_arguments() {
+        assert!(parse(&[]).is_err());
+        assert!(parse(&["--verbose", "a.xsb"]).is_err());
+        assert!(parse(&["--time-limit", "0", "a.xsb"]).is_err());
+        assert!(parse(&["--time-limit", "soon", "a.xsb"]).is_err());
+        assert!(parse(&["a.xsb", "--sort"]).is_err());
+        assert!(parse(&["--sort", "sorted.xsb", "a.xsb", "b.xsb"]).is_err());
+    }
+
+    #[test]
+    fn maps_the_summary_to_an_exit_code() {
+        let summary = |errors, warnings, inconclusive, io_errors| Summary {
+            levels: 1,
+            errors,
+            warnings,
+            inconclusive,
+            io_errors,
+        };
+        assert_eq!(summary(0, 0, 0, 0).exit_code(false), ExitCode::SUCCESS);
+        assert_eq!(summary(0, 1, 0, 0).exit_code(false), ExitCode::SUCCESS);
+        assert_eq!(
+            summary(0, 1, 0, 0).exit_code(true),
+            ExitCode::from(EXIT_PROBLEMS)
+        );
+        assert_eq!(
+            summary(1, 0, 1, 0).exit_code(false),
+            ExitCode::from(EXIT_PROBLEMS)
+        );
+        assert_eq!(
+            summary(0, 0, 1, 0).exit_code(false),
+            ExitCode::from(EXIT_INCONCLUSIVE)
+        );
+        assert_eq!(
+            summary(1, 0, 0, 1).exit_code(false),
+            ExitCode::from(EXIT_USAGE)
+        );
+    }
+}
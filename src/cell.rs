@@ -0,0 +1,64 @@
+use std::ops::{BitOr, BitOrAssign};
+
+// A single square of a level. A cell can combine a goal with either a block or the player.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash, Debug)]
+pub struct Cell(u8);
+
+pub type Layout = Vec<Vec<Cell>>;
+
+impl Cell {
+    pub const EMPTY: Cell = Cell(0);
+    pub const PLAYER: Cell = Cell(1);
+    pub const BLOCK: Cell = Cell(2);
+    pub const GOAL: Cell = Cell(4);
+    pub const WALL: Cell = Cell(8);
+
+    pub fn contains(self, other: Cell) -> bool {
+        self.0 & other.0 == other.0 && other.0 != 0
+    }
+
+    pub fn from_glyph(glyph: char) -> Option<Cell> {
+        match glyph {
+            '#' => Some(Cell::WALL),
+            '@' => Some(Cell::PLAYER),
+            '+' => Some(Cell::PLAYER | Cell::GOAL),
+            '$' => Some(Cell::BLOCK),
+            '*' => Some(Cell::BLOCK | Cell::GOAL),
+            '.' => Some(Cell::GOAL),
+            ' ' | '-' | '_' => Some(Cell::EMPTY),
+            _ => None,
+        }
+    }
+
+    pub fn glyph(self) -> char {
+        if self.contains(Cell::WALL) {
+            '#'
+        } else if self.contains(Cell::PLAYER | Cell::GOAL) {
+            '+'
+        } else if self.contains(Cell::PLAYER) {
+            '@'
+        } else if self.contains(Cell::BLOCK | Cell::GOAL) {
+            '*'
+        } else if self.contains(Cell::BLOCK) {
+            '$'
+        } else if self.contains(Cell::GOAL) {
+            '.'
+        } else {
+            ' '
+        }
+    }
+}
+
+impl BitOr for Cell {
+    type Output = Cell;
+
+    fn bitor(self, other: Cell) -> Cell {
+        Cell(self.0 | other.0)
+    }
+}
+
+impl BitOrAssign for Cell {
+    fn bitor_assign(&mut self, other: Cell) {
+        self.0 |= other.0;
+    }
+}
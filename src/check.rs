@@ -0,0 +1,203 @@
+use std::{collections::HashSet, fmt};
+
+use crate::{cell::Layout, get_enclosed_floor_positions, solver::Puzzle, Position};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Severity {
+    // The level can still be played, but probably isn't what its author meant.
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Problem {
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl Problem {
+    fn error(message: String) -> Problem {
+        Problem {
+            severity: Severity::Error,
+            message,
+        }
+    }
+
+    fn warning(message: String) -> Problem {
+        Problem {
+            severity: Severity::Warning,
+            message,
+        }
+    }
+}
+
+// Cells are reported the way a text editor shows them, counting from one.
+fn describe(position: Position) -> String {
+    format!("row {}, column {}", position.y + 1, position.x + 1)
+}
+
+pub fn check_layout(layout: &Layout) -> Vec<Problem> {
+    let Some(puzzle) = Puzzle::from_layout(layout) else {
+        return vec![Problem::error("level has no player".to_string())];
+    };
+    let mut problems = Vec::new();
+
+    let (blocks, goals) = (puzzle.blocks.len(), puzzle.goals.len());
+    if goals == 0 {
+        problems.push(Problem::error("level has no goals".to_string()));
+    } else if blocks < goals {
+        problems.push(Problem::error(format!(
+            "level has {} blocks for {} goals",
+            blocks, goals
+        )));
+    } else if blocks > goals {
+        problems.push(Problem::warning(format!(
+            "level has {} blocks for {} goals, the spare blocks are never needed",
+            blocks, goals
+        )));
+    }
+
+    let width = layout.first().map_or(0, |row| row.len()) as i32;
+    let height = layout.len() as i32;
+    let Some(floor) = get_enclosed_floor_positions(puzzle.player, &puzzle.walls, width, height)
+    else {
+        problems.push(Problem::error(
+            "the walls don't enclose the player, who can walk off the edge of the level"
+                .to_string(),
+        ));
+        return problems;
+    };
+    let floor: HashSet<Position> = floor.into_iter().collect();
+
+    let mut unreachable_goals: Vec<Position> = puzzle
+        .goals
+        .iter()
+        .filter(|goal| !floor.contains(*goal))
+        .copied()
+        .collect();
+    unreachable_goals.sort_by_key(|goal| (goal.y, goal.x));
+    for goal in unreachable_goals {
+        problems.push(Problem::error(format!(
+            "goal at {} can't be reached",
+            describe(goal)
+        )));
+    }
+
+    let mut unreachable_blocks: Vec<Position> = puzzle
+        .blocks
+        .iter()
+        .filter(|block| !floor.contains(*block))
+        .copied()
+        .collect();
+    unreachable_blocks.sort_by_key(|block| (block.y, block.x));
+    for block in unreachable_blocks {
+        problems.push(Problem::warning(format!(
+            "block at {} can't be reached",
+            describe(block)
+        )));
+    }
+
+    if goals > 0 && puzzle.goals.iter().all(|goal| puzzle.blocks.contains(goal)) {
+        problems.push(Problem::warning("level starts out solved".to_string()));
+    }
+
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::layout;
+
+    fn messages(rows: &[&str]) -> Vec<(Severity, String)> {
+        check_layout(&layout(rows))
+            .into_iter()
+            .map(|problem| (problem.severity, problem.message))
+            .collect()
+    }
+
+    fn error(message: &str) -> (Severity, String) {
+        (Severity::Error, message.to_string())
+    }
+
+    fn warning(message: &str) -> (Severity, String) {
+        (Severity::Warning, message.to_string())
+    }
+
+    #[test]
+    fn finds_nothing_wrong_with_a_good_level() {
+        assert!(messages(&["######", "#@$ .#", "######"]).is_empty());
+    }
+
+    #[test]
+    fn reports_a_missing_player() {
+        assert_eq!(
+            messages(&["#####", "# $.#", "#####"]),
+            [error("level has no player")]
+        );
+    }
+
+    #[test]
+    fn reports_a_level_without_goals() {
+        assert_eq!(
+            messages(&["#####", "#@$ #", "#####"]),
+            [error("level has no goals")]
+        );
+    }
+
+    #[test]
+    fn reports_too_few_blocks() {
+        assert_eq!(
+            messages(&["######", "#@$..#", "######"]),
+            [error("level has 1 blocks for 2 goals")]
+        );
+    }
+
+    #[test]
+    fn reports_a_gap_in_the_walls() {
+        assert_eq!(
+            messages(&["######", "#@$.  ", "######"]),
+            [error(
+                "the walls don't enclose the player, who can walk off the edge of the level"
+            )]
+        );
+    }
+
+    #[test]
+    fn reports_goals_and_blocks_the_player_cannot_reach() {
+        assert_eq!(
+            messages(&["########", "#@$.#$.#", "########"]),
+            [
+                error("goal at row 2, column 7 can't be reached"),
+                warning("block at row 2, column 6 can't be reached"),
+            ]
+        );
+    }
+
+    #[test]
+    fn warns_about_spare_blocks() {
+        assert_eq!(
+            messages(&["######", "#@$$.#", "######"]),
+            [warning(
+                "level has 2 blocks for 1 goals, the spare blocks are never needed"
+            )]
+        );
+    }
+
+    #[test]
+    fn warns_about_a_level_that_starts_solved() {
+        assert_eq!(
+            messages(&["#####", "#@* #", "#####"]),
+            [warning("level starts out solved")]
+        );
+    }
+}
@@ -0,0 +1,246 @@
+use bevy::{prelude::*, window::PrimaryWindow};
+use bevy_sokoban::{
+    path::{find_push, find_walk},
+    Position,
+};
+
+use crate::{
+    deadlock_plugin::{DeadlockWarning, DEADLOCK_COLOR},
+    hint_plugin::{Hint, HINT_COLOR},
+    play_plugin::{LevelState, Player},
+    tiles::{Translation, TILE_SIZE},
+    GameState,
+};
+
+pub struct ClickPlugin;
+
+const SELECTED_COLOR: Color = Color::rgb(0.6, 1.0, 0.6);
+const REFUSED_COLOR: Color = Color::rgb(1.0, 0.4, 0.4);
+const SNAP_BACK_SECONDS: f32 = 0.25;
+
+#[derive(Resource, Default)]
+pub struct SelectedBlock(Option<(Position, Entity)>);
+
+#[derive(Resource, Default)]
+pub struct Drag(Option<DraggedBlock>);
+
+struct DraggedBlock {
+    entity: Entity,
+    grab_offset: Vec2,
+}
+
+#[derive(Component)]
+struct SnapBack {
+    from: Vec3,
+    to: Vec3,
+    timer: Timer,
+}
+
+// The tint a block goes back to when it is no longer selected or refused.
+fn block_color(entity: Entity, deadlock_warning: &DeadlockWarning, hint: &Hint) -> Color {
+    if deadlock_warning.tinted_blocks.contains(&entity) {
+        DEADLOCK_COLOR
+    } else if hint.highlighted_block == Some(entity) {
+        HINT_COLOR
+    } else {
+        Color::WHITE
+    }
+}
+
+// Play input is ignored while a block is selected or dragged.
+pub fn is_holding_block(selected_block: Res<SelectedBlock>, drag: Res<Drag>) -> bool {
+    selected_block.0.is_some() || drag.0.is_some()
+}
+
+fn select_block(
+    selected_block: &mut SelectedBlock,
+    selection: Option<(Position, Entity)>,
+    sprite_query: &mut Query<&mut Sprite>,
+    tint: impl Fn(Entity) -> Color,
+) {
+    if let Some((_, entity)) = selected_block.0 {
+        if let Ok(mut sprite) = sprite_query.get_mut(entity) {
+            sprite.color = tint(entity);
+        }
+    }
+    if let Some((_, entity)) = selection {
+        if let Ok(mut sprite) = sprite_query.get_mut(entity) {
+            sprite.color = SELECTED_COLOR;
+        }
+    }
+    selected_block.0 = selection;
+}
+
+fn cursor_world_position(
+    window_query: &Query<&Window, With<PrimaryWindow>>,
+    camera_query: &Query<(&Camera, &GlobalTransform)>,
+) -> Option<Vec2> {
+    let cursor_position = window_query.get_single().ok()?.cursor_position()?;
+    let (camera, camera_transform) = camera_query.get_single().ok()?;
+    camera.viewport_to_world_2d(camera_transform, cursor_position)
+}
+
+// Clicking floor walks there. Clicking a block selects it, and the next click pushes it there.
+#[allow(clippy::too_many_arguments)]
+fn click_to_move(
+    mouse_input: Res<Input<MouseButton>>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Camera, &GlobalTransform)>,
+    mut level_state: ResMut<LevelState>,
+    mut selected_block: ResMut<SelectedBlock>,
+    mut drag: ResMut<Drag>,
+    player_query: Query<&Player>,
+    mut sprite_query: Query<&mut Sprite>,
+    deadlock_warning: Res<DeadlockWarning>,
+    hint: Res<Hint>,
+) {
+    let tint = |entity| block_color(entity, &deadlock_warning, &hint);
+    // Any move, including a new level, leaves the selection pointing at the wrong cell.
+    if level_state.is_changed() && selected_block.0.is_some() {
+        select_block(&mut selected_block, None, &mut sprite_query, tint);
+    }
+
+    if !mouse_input.just_pressed(MouseButton::Left) {
+        return;
+    }
+    let is_moving = player_query.iter().any(|player| player.is_moving);
+    if is_moving || level_state.history.is_walking() {
+        return;
+    }
+    let Some(cursor) = cursor_world_position(&window_query, &camera_query) else {
+        return;
+    };
+    let clicked = Position::from_translation(cursor.extend(0.0));
+
+    let puzzle = level_state.game().puzzle();
+    let clicked_block = level_state
+        .blocks
+        .get(&clicked)
+        .map(|entity| (clicked, *entity));
+    if let Some((_, entity)) = clicked_block {
+        drag.0 = Some(DraggedBlock {
+            entity,
+            grab_offset: clicked.to_translation().truncate() - cursor,
+        });
+    }
+    let moves = match (selected_block.0, clicked_block) {
+        (Some((block, _)), _) if block == clicked => {
+            select_block(&mut selected_block, None, &mut sprite_query, tint);
+            return;
+        }
+        (_, Some(selection)) => {
+            select_block(
+                &mut selected_block,
+                Some(selection),
+                &mut sprite_query,
+                tint,
+            );
+            return;
+        }
+        (Some((block, _)), None) => {
+            select_block(&mut selected_block, None, &mut sprite_query, tint);
+            find_push(puzzle, block, clicked)
+        }
+        (None, None) => find_walk(puzzle, clicked),
+    };
+
+    if let Some(moves) = moves.filter(|moves| !moves.is_empty()) {
+        level_state.history.start_walk(moves);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn drag_block(
+    mut commands: Commands,
+    mouse_input: Res<Input<MouseButton>>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Camera, &GlobalTransform)>,
+    mut level_state: ResMut<LevelState>,
+    mut selected_block: ResMut<SelectedBlock>,
+    mut drag: ResMut<Drag>,
+    mut transform_query: Query<&mut Transform>,
+    mut sprite_query: Query<&mut Sprite>,
+    deadlock_warning: Res<DeadlockWarning>,
+    hint: Res<Hint>,
+) {
+    let tint = |entity| block_color(entity, &deadlock_warning, &hint);
+    let Some(dragged) = &drag.0 else {
+        return;
+    };
+    let Ok(mut transform) = transform_query.get_mut(dragged.entity) else {
+        drag.0 = None;
+        return;
+    };
+
+    if mouse_input.pressed(MouseButton::Left) {
+        if let Some(cursor) = cursor_world_position(&window_query, &camera_query) {
+            transform.translation = (cursor + dragged.grab_offset).extend(3.0);
+        }
+        return;
+    }
+
+    let entity = dragged.entity;
+    drag.0 = None;
+    let Some(block) = level_state
+        .blocks
+        .iter()
+        .find(|(_, block_entity)| **block_entity == entity)
+        .map(|(position, _)| *position)
+    else {
+        return;
+    };
+    let dropped_on = Position::from_translation(
+        transform.translation + Vec3::new(TILE_SIZE / 2.0, -TILE_SIZE / 2.0, 0.0),
+    );
+    if dropped_on == block {
+        transform.translation = block.to_translation();
+        return;
+    }
+
+    select_block(&mut selected_block, None, &mut sprite_query, tint);
+    match find_push(level_state.game().puzzle(), block, dropped_on) {
+        Some(moves) if !moves.is_empty() => {
+            transform.translation = block.to_translation();
+            level_state.history.start_walk(moves);
+        }
+        _ => {
+            commands.entity(entity).insert(SnapBack {
+                from: transform.translation,
+                to: block.to_translation(),
+                timer: Timer::from_seconds(SNAP_BACK_SECONDS, TimerMode::Once),
+            });
+            if let Ok(mut sprite) = sprite_query.get_mut(entity) {
+                sprite.color = REFUSED_COLOR;
+            }
+        }
+    }
+}
+
+fn snap_back(
+    time: Res<Time>,
+    mut commands: Commands,
+    mut snap_back_query: Query<(Entity, &mut SnapBack, &mut Transform, &mut Sprite)>,
+    deadlock_warning: Res<DeadlockWarning>,
+    hint: Res<Hint>,
+) {
+    for (entity, mut snap_back, mut transform, mut sprite) in &mut snap_back_query {
+        snap_back.timer.tick(time.delta());
+        transform.translation = snap_back.from.lerp(snap_back.to, snap_back.timer.percent());
+        if snap_back.timer.finished() {
+            sprite.color = block_color(entity, &deadlock_warning, &hint);
+            commands.entity(entity).remove::<SnapBack>();
+        }
+    }
+}
+
+impl Plugin for ClickPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(SelectedBlock::default())
+            .insert_resource(Drag::default())
+            .add_systems(
+                Update,
+                (click_to_move, drag_block.after(click_to_move), snap_back)
+                    .run_if(in_state(GameState::Playing)),
+            );
+    }
+}
@@ -0,0 +1,196 @@
+use std::collections::{HashSet, VecDeque};
+
+use crate::{solver::Puzzle, Direction, Position};
+
+const CORRAL_SEARCH_LIMIT: usize = 2_000;
+
+// The blocks to blame when the level can no longer be won.
+pub fn find_deadlock(
+    puzzle: &Puzzle,
+    dead_squares: &HashSet<Position>,
+) -> Option<HashSet<Position>> {
+    let frozen_blocks = frozen_blocks(puzzle);
+    let stuck_blocks: HashSet<Position> = puzzle
+        .blocks
+        .iter()
+        .filter(|block| !puzzle.goals.contains(*block))
+        .filter(|block| dead_squares.contains(*block) || frozen_blocks.contains(*block))
+        .copied()
+        .collect();
+    if puzzle.blocks.len() - stuck_blocks.len() < puzzle.goals.len() {
+        return Some(stuck_blocks);
+    }
+
+    corral_deadlock(puzzle)
+}
+
+fn frozen_blocks(puzzle: &Puzzle) -> HashSet<Position> {
+    puzzle
+        .blocks
+        .iter()
+        .filter(|block| is_frozen(puzzle, **block, &mut HashSet::new()))
+        .copied()
+        .collect()
+}
+
+// Blocks already being checked count as walls, so rings of blocks don't recurse forever.
+fn is_frozen(puzzle: &Puzzle, block: Position, checking: &mut HashSet<Position>) -> bool {
+    checking.insert(block);
+    let frozen = is_axis_blocked(puzzle, block, Direction::Left, checking)
+        && is_axis_blocked(puzzle, block, Direction::Up, checking);
+    checking.remove(&block);
+    frozen
+}
+
+fn is_axis_blocked(
+    puzzle: &Puzzle,
+    block: Position,
+    direction: Direction,
+    checking: &mut HashSet<Position>,
+) -> bool {
+    [direction, direction.opposite()].into_iter().any(|side| {
+        let neighbour = block.step(side);
+        puzzle.walls.contains(&neighbour)
+            || checking.contains(&neighbour)
+            || (puzzle.blocks.contains(&neighbour) && is_frozen(puzzle, neighbour, checking))
+    })
+}
+
+fn reachable_cells(
+    player: Position,
+    walls: &HashSet<Position>,
+    blocks: &HashSet<Position>,
+) -> HashSet<Position> {
+    let mut visited = HashSet::new();
+    let mut to_visit = vec![player];
+    while let Some(position) = to_visit.pop() {
+        if walls.contains(&position) || blocks.contains(&position) || !visited.insert(position) {
+            continue;
+        }
+        to_visit.extend(Direction::ALL.map(|direction| position.step(direction)));
+    }
+    visited
+}
+
+// A corral is floor the player is shut out of. It is a deadlock when it holds an uncovered goal
+// and no pushes from outside ever open it or fill its goals.
+fn corral_deadlock(puzzle: &Puzzle) -> Option<HashSet<Position>> {
+    let floor = puzzle.floor_positions()?;
+    let reachable = reachable_cells(puzzle.player, &puzzle.walls, &puzzle.blocks);
+    let corral: HashSet<Position> = floor
+        .into_iter()
+        .filter(|position| !reachable.contains(position) && !puzzle.blocks.contains(position))
+        .collect();
+    let corral_goals: Vec<Position> = corral
+        .iter()
+        .filter(|position| puzzle.goals.contains(*position))
+        .copied()
+        .collect();
+    if corral_goals.is_empty() {
+        return None;
+    }
+
+    let state_key = |blocks: &HashSet<Position>, reachable: &HashSet<Position>| {
+        let mut sorted_blocks: Vec<(i32, i32)> = blocks.iter().map(|b| (b.y, b.x)).collect();
+        sorted_blocks.sort_unstable();
+        let player = reachable.iter().map(|p| (p.y, p.x)).min();
+        (sorted_blocks, player)
+    };
+
+    let mut seen = HashSet::new();
+    seen.insert(state_key(&puzzle.blocks, &reachable));
+    let mut to_visit = VecDeque::from([(puzzle.blocks.clone(), reachable)]);
+    while let Some((blocks, reachable)) = to_visit.pop_front() {
+        if seen.len() > CORRAL_SEARCH_LIMIT {
+            return None;
+        }
+
+        for block in &blocks {
+            for direction in Direction::ALL {
+                let ahead = block.step(direction);
+                if !reachable.contains(&block.step(direction.opposite()))
+                    || puzzle.walls.contains(&ahead)
+                    || blocks.contains(&ahead)
+                {
+                    continue;
+                }
+
+                let mut next_blocks = blocks.clone();
+                next_blocks.remove(block);
+                next_blocks.insert(ahead);
+                let next_reachable = reachable_cells(*block, &puzzle.walls, &next_blocks);
+                let opened = next_reachable.iter().any(|cell| corral.contains(cell));
+                let filled = corral_goals.iter().all(|goal| next_blocks.contains(goal));
+                if opened || filled {
+                    return None;
+                }
+
+                if seen.insert(state_key(&next_blocks, &next_reachable)) {
+                    to_visit.push_back((next_blocks, next_reachable));
+                }
+            }
+        }
+    }
+
+    Some(
+        puzzle
+            .blocks
+            .iter()
+            .filter(|block| {
+                Direction::ALL
+                    .iter()
+                    .any(|direction| corral.contains(&block.step(*direction)))
+            })
+            .copied()
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fixtures::puzzle, solver::dead_squares};
+
+    fn positions(cells: &[(i32, i32)]) -> HashSet<Position> {
+        cells
+            .iter()
+            .map(|(x, y)| Position { x: *x, y: *y })
+            .collect()
+    }
+
+    #[test]
+    fn blocks_holding_each_other_against_a_wall_are_frozen() {
+        let puzzle = puzzle(&["#######", "# $$  #", "#  $ .#", "# @ ..#", "#######"]);
+        assert_eq!(frozen_blocks(&puzzle), positions(&[(2, 1), (3, 1)]));
+        assert_eq!(
+            find_deadlock(&puzzle, &dead_squares(&puzzle)),
+            Some(positions(&[(2, 1), (3, 1)]))
+        );
+    }
+
+    #[test]
+    fn frozen_blocks_on_goals_are_no_deadlock() {
+        let puzzle = puzzle(&["######", "#**  #", "#  @ #", "######"]);
+        assert_eq!(frozen_blocks(&puzzle).len(), 2);
+        assert_eq!(find_deadlock(&puzzle, &dead_squares(&puzzle)), None);
+    }
+
+    #[test]
+    fn a_goal_shut_in_a_corral_that_cannot_be_opened_is_a_deadlock() {
+        let puzzle = puzzle(&["######", "#.   #", "####$#", "# @  #", "######"]);
+        assert!(frozen_blocks(&puzzle).is_empty());
+        assert_eq!(corral_deadlock(&puzzle), Some(positions(&[(4, 2)])));
+    }
+
+    #[test]
+    fn a_corral_whose_goal_a_push_can_cover_is_no_deadlock() {
+        let puzzle = puzzle(&["######", "#   .#", "####$#", "# @  #", "######"]);
+        assert_eq!(corral_deadlock(&puzzle), None);
+    }
+
+    #[test]
+    fn a_level_with_a_gap_in_its_walls_has_no_corral() {
+        let puzzle = puzzle(&["######", "#.   #", "####$#", "# @   ", "######"]);
+        assert_eq!(corral_deadlock(&puzzle), None);
+    }
+}
@@ -0,0 +1,97 @@
+use std::collections::HashSet;
+
+use bevy::prelude::*;
+
+use bevy_sokoban::{deadlock::find_deadlock, Position};
+
+use crate::{
+    action_plugin::{Action, Bindings},
+    play_plugin::LevelState,
+    GameState,
+};
+
+pub struct DeadlockPlugin;
+
+pub const DEADLOCK_COLOR: Color = Color::rgb(1.0, 0.4, 0.4);
+
+#[derive(Resource, Default, Deref)]
+pub struct DeadSquares(pub HashSet<Position>);
+
+#[derive(Resource, Default)]
+pub struct DeadlockWarning {
+    pub tinted_blocks: Vec<Entity>,
+}
+
+#[derive(Component)]
+struct DeadlockText;
+
+fn check_deadlock(
+    mut commands: Commands,
+    level_state: Res<LevelState>,
+    dead_squares: Res<DeadSquares>,
+    bindings: Res<Bindings>,
+    mut deadlock_warning: ResMut<DeadlockWarning>,
+    text_query: Query<Entity, With<DeadlockText>>,
+    mut sprite_query: Query<&mut Sprite>,
+) {
+    if !level_state.is_changed() {
+        return;
+    }
+
+    for block_entity in deadlock_warning.tinted_blocks.drain(..) {
+        if let Ok(mut sprite) = sprite_query.get_mut(block_entity) {
+            sprite.color = Color::WHITE;
+        }
+    }
+    for entity in text_query.iter() {
+        commands.entity(entity).despawn();
+    }
+
+    let Some(stuck_blocks) = find_deadlock(level_state.game().puzzle(), &dead_squares) else {
+        return;
+    };
+
+    for position in stuck_blocks {
+        let Some(block_entity) = level_state.blocks.get(&position) else {
+            continue;
+        };
+        if let Ok(mut sprite) = sprite_query.get_mut(*block_entity) {
+            sprite.color = DEADLOCK_COLOR;
+        }
+        deadlock_warning.tinted_blocks.push(*block_entity);
+    }
+
+    commands.spawn((
+        DeadlockText,
+        TextBundle::from_section(
+            format!(
+                "Deadlock! This level can't be won anymore, press {} to undo or {} to restart",
+                bindings.key_label(Action::Undo),
+                bindings.key_label(Action::Restart)
+            ),
+            TextStyle {
+                font_size: 14.0,
+                color: DEADLOCK_COLOR,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(5.0),
+            left: Val::Px(5.0),
+            ..default()
+        }),
+    ));
+}
+
+impl Plugin for DeadlockPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(DeadSquares::default())
+            .insert_resource(DeadlockWarning::default())
+            .add_systems(
+                Update,
+                check_deadlock
+                    .run_if(in_state(GameState::Playing).or_else(in_state(GameState::Replaying))),
+            );
+    }
+}
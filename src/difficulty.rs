@@ -0,0 +1,67 @@
+use crate::{
+    game::{Game, MoveResult},
+    solver::{dead_squares, solve, Puzzle, SolveError, SolverConfig},
+    Direction, Position,
+};
+
+#[derive(Clone, Copy, Debug)]
+pub struct Difficulty {
+    pub score: f32,
+    pub moves: usize,
+    pub pushes: usize,
+    pub block_lines: usize,
+    pub block_changes: usize,
+    pub nodes: usize,
+    pub dead_square_ratio: f32,
+}
+
+// The search size is counted on a log scale so a single huge search doesn't swamp the rest.
+pub fn estimate_difficulty(
+    puzzle: &Puzzle,
+    config: &SolverConfig,
+) -> Result<Difficulty, SolveError> {
+    let solution = solve(puzzle, config)?;
+
+    let mut game = Game::new(puzzle.clone());
+    let mut last_push: Option<(Position, Direction)> = None;
+    let mut block_lines = 0;
+    let mut block_changes = 0;
+    for direction in &solution.moves {
+        let block = game.player().step(*direction);
+        if game.try_move(*direction) != MoveResult::Pushed {
+            continue;
+        }
+        match last_push {
+            Some((last_block, last_direction)) if last_block == block => {
+                if last_direction != *direction {
+                    block_lines += 1;
+                }
+            }
+            _ => {
+                block_lines += 1;
+                block_changes += 1;
+            }
+        }
+        last_push = Some((block.step(*direction), *direction));
+    }
+
+    let dead_square_ratio = match puzzle.floor_positions() {
+        Some(floor) => dead_squares(puzzle).len() as f32 / floor.len().max(1) as f32,
+        None => 0.0,
+    };
+
+    let score = solution.pushes as f32 * 0.5
+        + block_lines as f32
+        + block_changes as f32 * 2.0
+        + (solution.nodes.max(1) as f32).log2() * 2.0
+        + dead_square_ratio * 10.0;
+    Ok(Difficulty {
+        score,
+        moves: solution.moves.len(),
+        pushes: solution.pushes,
+        block_lines,
+        block_changes,
+        nodes: solution.nodes,
+        dead_square_ratio,
+    })
+}
@@ -1,6 +1,32 @@
-use bevy::{prelude::*, sprite::Anchor, utils::HashMap};
-
-use crate::{tiles::spawn_floor, GameState, Position, TILE_SIZE};
+use std::{
+    collections::HashSet,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use bevy::{
+    prelude::*,
+    sprite::Anchor,
+    tasks::{block_on, AsyncComputeTaskPool, Task},
+    utils::HashMap,
+};
+
+use bevy_sokoban::{
+    cell::{Cell, Layout},
+    generator::{generate, GeneratorConfig},
+    get_enclosed_floor_positions,
+    levels::{is_enclosed, list_user_levels, load_collection, save_user_level, user_level_path},
+    solver::{solve, Puzzle, Solution, SolveError, SolverConfig},
+    Direction, Position,
+};
+
+use crate::{
+    action_plugin::{Action, Actions, Bindings},
+    play_plugin::{Playtest, PlaytestEvent},
+    tiles::{
+        spawn_block, spawn_floor, spawn_goal, spawn_player, spawn_wall, Translation, TILE_SIZE,
+    },
+    GameState,
+};
 
 pub struct EditPlugin;
 
@@ -14,28 +40,86 @@ struct EditingState {
 }
 
 impl EditingState {
-    fn can_place(&self, position: &Position) -> bool {
+    fn is_player(&self, position: &Position) -> bool {
+        self.player
+            .is_some_and(|(player_position, _)| &player_position == position)
+    }
+
+    fn can_place_object(&self, position: &Position) -> bool {
         self.floors.contains_key(position)
             && !self.blocks.contains_key(position)
-            && !self.goals.contains_key(position)
-            && (self.player.is_none() || &self.player.unwrap().0 != position)
+            && !self.is_player(position)
+    }
+
+    fn can_place_goal(&self, position: &Position) -> bool {
+        self.floors.contains_key(position) && !self.goals.contains_key(position)
     }
 
     fn remove_object(&mut self, position: &Position) -> Option<Entity> {
-        if self.blocks.contains_key(position) {
-            return self.blocks.remove(position);
-        } else if self.goals.contains_key(position) {
-            return self.goals.remove(position);
-        } else if self.player.is_some() && self.player.unwrap().0 == *position {
-            let player_id = self.player.unwrap().1;
+        if let Some(block_id) = self.blocks.remove(position) {
+            Some(block_id)
+        } else if let Some((_, player_id)) = self.player.filter(|_| self.is_player(position)) {
             self.player = None;
-            return Some(player_id);
+            Some(player_id)
         } else {
-            return None;
+            self.goals.remove(position)
+        }
+    }
+
+    fn despawn_all(&mut self, commands: &mut Commands) {
+        let entities = self
+            .floors
+            .drain()
+            .chain(self.walls.drain())
+            .chain(self.blocks.drain())
+            .chain(self.goals.drain())
+            .chain(self.player.take());
+        for (_, entity) in entities {
+            commands.entity(entity).despawn();
+        }
+    }
+
+    fn snapshot(&self) -> EditorLevel {
+        EditorLevel {
+            floors: self.floors.keys().copied().collect(),
+            walls: self.walls.keys().copied().collect(),
+            blocks: self.blocks.keys().copied().collect(),
+            goals: self.goals.keys().copied().collect(),
+            player: self.player.map(|(position, _)| position),
         }
     }
 
-    fn serialize(&self) -> Vec<Vec<i32>> {
+    fn restore(
+        &mut self,
+        commands: &mut Commands,
+        asset_server: &Res<AssetServer>,
+        level: &EditorLevel,
+    ) {
+        self.despawn_all(commands);
+
+        for position in &level.floors {
+            let floor_id = commands.spawn(spawn_floor(asset_server, *position)).id();
+            self.floors.insert(*position, floor_id);
+        }
+        for position in &level.walls {
+            let wall_id = commands.spawn(spawn_wall(asset_server, *position)).id();
+            self.walls.insert(*position, wall_id);
+        }
+        for position in &level.goals {
+            let goal_id = commands.spawn(spawn_goal(asset_server, *position)).id();
+            self.goals.insert(*position, goal_id);
+        }
+        for position in &level.blocks {
+            let block_id = commands.spawn(spawn_block(asset_server, *position)).id();
+            self.blocks.insert(*position, block_id);
+        }
+        if let Some(position) = level.player {
+            let player_id = commands.spawn(spawn_player(asset_server, position)).id();
+            self.player = Some((position, player_id));
+        }
+    }
+
+    fn serialize(&self) -> Layout {
         let wall_positions = self.walls.keys();
         let min_x = wall_positions.clone().map(|p| p.x).min().unwrap();
         let max_x = wall_positions.clone().map(|p| p.x).max().unwrap();
@@ -43,28 +127,130 @@ impl EditingState {
         let max_y = wall_positions.clone().map(|p| p.y).max().unwrap();
 
         let mut level = vec![
-            vec![0; (1 + max_x - min_x).try_into().unwrap()];
+            vec![Cell::EMPTY; (1 + max_x - min_x).try_into().unwrap()];
             (1 + max_y - min_y).try_into().unwrap()
         ];
 
         for wall_position in wall_positions {
-            level[(wall_position.y - min_y) as usize][(wall_position.x - min_x) as usize] = 8;
+            level[(wall_position.y - min_y) as usize][(wall_position.x - min_x) as usize] |=
+                Cell::WALL;
         }
 
         for goal_position in self.goals.keys() {
-            level[(goal_position.y - min_y) as usize][(goal_position.x - min_x) as usize] = 4;
+            level[(goal_position.y - min_y) as usize][(goal_position.x - min_x) as usize] |=
+                Cell::GOAL;
         }
 
         for block_position in self.blocks.keys() {
-            level[(block_position.y - min_y) as usize][(block_position.x - min_x) as usize] = 2;
+            level[(block_position.y - min_y) as usize][(block_position.x - min_x) as usize] |=
+                Cell::BLOCK;
         }
 
         if let Some((player_position, _)) = self.player {
-            level[(player_position.y - min_y) as usize][(player_position.x - min_x) as usize] = 1;
+            level[(player_position.y - min_y) as usize][(player_position.x - min_x) as usize] |=
+                Cell::PLAYER;
         }
 
         level
     }
+
+    fn playable_layout(&self, before: &str) -> Result<Layout, String> {
+        if self.player.is_none() {
+            return Err(format!("Place the player before {}", before));
+        }
+        let layout = (!self.walls.is_empty()).then(|| self.serialize());
+        match layout {
+            Some(layout) if is_enclosed(&layout) => Ok(layout),
+            _ => Err(format!(
+                "Close the walls around the player before {}",
+                before
+            )),
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+struct EditorLevel {
+    floors: HashSet<Position>,
+    walls: HashSet<Position>,
+    blocks: HashSet<Position>,
+    goals: HashSet<Position>,
+    player: Option<Position>,
+}
+
+impl EditorLevel {
+    // Floors are not part of the layout, so they are rebuilt from the player's reach.
+    fn from_layout(layout: &Layout) -> EditorLevel {
+        let mut level = EditorLevel::default();
+        for (row_index, row) in layout.iter().enumerate() {
+            for (col_index, cell) in row.iter().enumerate() {
+                let position = Position {
+                    x: col_index as i32,
+                    y: row_index as i32,
+                };
+
+                if cell.contains(Cell::WALL) {
+                    level.walls.insert(position);
+                }
+                if cell.contains(Cell::GOAL) {
+                    level.goals.insert(position);
+                }
+                if cell.contains(Cell::BLOCK) {
+                    level.blocks.insert(position);
+                }
+                if cell.contains(Cell::PLAYER) {
+                    level.player = Some(position);
+                }
+            }
+        }
+
+        level
+            .floors
+            .extend(level.goals.iter().chain(level.blocks.iter()));
+        let width = layout.first().map_or(0, |row| row.len()) as i32;
+        let height = layout.len() as i32;
+        if let Some(player_position) = level.player {
+            let floors = get_enclosed_floor_positions(player_position, &level.walls, width, height);
+            level.floors.extend(floors.unwrap_or_default());
+        }
+        level
+    }
+}
+
+#[derive(Resource)]
+struct SolverTask(Task<Result<Solution, SolveError>>);
+
+#[derive(Resource)]
+struct GeneratorTask(Task<Option<Layout>>);
+
+#[derive(Resource)]
+struct PlaytestSnapshot {
+    level: EditorLevel,
+    cursor_position: Position,
+    level_name: Option<String>,
+}
+
+#[derive(Default)]
+enum FileDialog {
+    #[default]
+    Closed,
+    Save {
+        name: String,
+    },
+    ConfirmOverwrite {
+        name: String,
+    },
+    Open {
+        names: Vec<String>,
+        selected: usize,
+    },
+}
+
+#[derive(Resource, Default)]
+struct EditorFiles {
+    level_name: Option<String>,
+    dialog: FileDialog,
+    message: String,
 }
 
 #[derive(Component)]
@@ -72,13 +258,20 @@ struct Cursor {
     action_timer: Timer,
 }
 
+#[derive(Component)]
+struct EditorText;
+
 fn remove_level(mut commands: Commands, almost_everything_query: Query<Entity, Without<Window>>) {
     for entity in almost_everything_query.iter() {
         commands.entity(entity).despawn();
     }
 }
 
-fn show_cursor(mut commands: Commands, asset_server: Res<AssetServer>) {
+fn show_cursor(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    playtest_snapshot: Option<Res<PlaytestSnapshot>>,
+) {
     let camera_position = Vec3::new(TILE_SIZE / 2.0, -(TILE_SIZE) / 2.0, 1000.0);
     commands.spawn(Camera2dBundle {
         transform: Transform {
@@ -89,6 +282,9 @@ fn show_cursor(mut commands: Commands, asset_server: Res<AssetServer>) {
         ..default()
     });
 
+    let cursor_position = playtest_snapshot
+        .as_ref()
+        .map_or(Position { x: 0, y: 0 }, |snapshot| snapshot.cursor_position);
     commands.spawn((
         Cursor {
             action_timer: Timer::from_seconds(0.2, TimerMode::Once),
@@ -99,19 +295,347 @@ fn show_cursor(mut commands: Commands, asset_server: Res<AssetServer>) {
                 ..default()
             },
             texture: asset_server.load("cursor.png"),
-            transform: Transform::from_translation(Vec3::new(0.0, 0.0, 2.0)),
+            transform: Transform::from_translation(cursor_position.to_translation_z(2.0)),
             ..default()
         },
     ));
 
-    commands.insert_resource(EditingState::default());
+    commands.spawn((
+        EditorText,
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 14.0,
+                color: Color::WHITE,
+                ..default()
+            },
+        ),
+    ));
+
+    let mut editing_state = EditingState::default();
+    let mut editor_files = EditorFiles::default();
+    if let Some(snapshot) = playtest_snapshot {
+        editing_state.restore(&mut commands, &asset_server, &snapshot.level);
+        editor_files.level_name = snapshot.level_name.clone();
+        commands.remove_resource::<PlaytestSnapshot>();
+        commands.remove_resource::<Playtest>();
+    }
+    commands.insert_resource(editing_state);
+    commands.insert_resource(editor_files);
+}
+
+fn start_playtest(
+    mut commands: Commands,
+    actions: Res<Actions>,
+    mut editor_files: ResMut<EditorFiles>,
+    editing_state: Res<EditingState>,
+    cursor_query: Query<&Transform, With<Cursor>>,
+    mut playtest_writer: EventWriter<PlaytestEvent>,
+    mut game_state: ResMut<NextState<GameState>>,
+) {
+    if !actions.just_pressed(Action::Playtest) {
+        return;
+    }
+    let Some(cursor_transform) = cursor_query.iter().next() else {
+        return;
+    };
+    let layout = match editing_state.playable_layout("playtesting") {
+        Ok(layout) => layout,
+        Err(message) => {
+            editor_files.message = message;
+            return;
+        }
+    };
+
+    commands.insert_resource(PlaytestSnapshot {
+        level: editing_state.snapshot(),
+        cursor_position: Position::from_translation(cursor_transform.translation),
+        level_name: editor_files.level_name.clone(),
+    });
+    commands.insert_resource(Playtest);
+    playtest_writer.send(PlaytestEvent(layout));
+    game_state.set(GameState::Playing);
+}
+
+fn file_dialog_closed(editor_files: Res<EditorFiles>) -> bool {
+    matches!(editor_files.dialog, FileDialog::Closed)
+}
+
+fn save_editing_state(editing_state: &EditingState, name: &str) -> String {
+    match save_user_level(name, &editing_state.serialize()) {
+        Ok(path) => format!("Saved {}", path.display()),
+        Err(error) => format!("Could not save {}: {}", name, error),
+    }
+}
+
+fn check_solvable(
+    mut commands: Commands,
+    actions: Res<Actions>,
+    editing_state: Res<EditingState>,
+    mut editor_files: ResMut<EditorFiles>,
+    solver_task: Option<Res<SolverTask>>,
+) {
+    if !actions.just_pressed(Action::CheckLevel) || solver_task.is_some() {
+        return;
+    }
+    let layout = match editing_state.playable_layout("checking the level") {
+        Ok(layout) => layout,
+        Err(message) => {
+            editor_files.message = message;
+            return;
+        }
+    };
+    let Some(puzzle) = Puzzle::from_layout(&layout) else {
+        return;
+    };
+
+    let task =
+        AsyncComputeTaskPool::get().spawn(async move { solve(&puzzle, &SolverConfig::default()) });
+    commands.insert_resource(SolverTask(task));
+    editor_files.message = "Solving...".to_string();
+}
+
+fn report_solution(
+    mut commands: Commands,
+    solver_task: Option<ResMut<SolverTask>>,
+    mut editor_files: ResMut<EditorFiles>,
+) {
+    let Some(mut solver_task) = solver_task else {
+        return;
+    };
+    if !solver_task.0.is_finished() {
+        return;
+    }
+
+    editor_files.message = match block_on(&mut solver_task.0) {
+        Ok(solution) => format!(
+            "Solvable in {} pushes and {} moves ({} positions searched)",
+            solution.pushes,
+            solution.moves.len(),
+            solution.nodes
+        ),
+        Err(error) => format!("Not solvable: {}", error),
+    };
+    commands.remove_resource::<SolverTask>();
+}
+
+fn start_generating(
+    mut commands: Commands,
+    actions: Res<Actions>,
+    mut editor_files: ResMut<EditorFiles>,
+    generator_task: Option<Res<GeneratorTask>>,
+) {
+    if !actions.just_pressed(Action::GenerateLevel) || generator_task.is_some() {
+        return;
+    }
+
+    let seed = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_nanos() as u64);
+    let config = GeneratorConfig {
+        seed,
+        ..GeneratorConfig::default()
+    };
+    let task = AsyncComputeTaskPool::get().spawn(async move { generate(&config) });
+    commands.insert_resource(GeneratorTask(task));
+    editor_files.message = "Generating...".to_string();
+}
+
+fn place_generated_level(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    generator_task: Option<ResMut<GeneratorTask>>,
+    bindings: Res<Bindings>,
+    mut editing_state: ResMut<EditingState>,
+    mut editor_files: ResMut<EditorFiles>,
+) {
+    let Some(mut generator_task) = generator_task else {
+        return;
+    };
+    if !generator_task.0.is_finished() {
+        return;
+    }
+
+    match block_on(&mut generator_task.0) {
+        Some(layout) => {
+            let level = EditorLevel::from_layout(&layout);
+            editing_state.restore(&mut commands, &asset_server, &level);
+            editor_files.level_name = None;
+            editor_files.message = "Generated a new level".to_string();
+        }
+        None => {
+            editor_files.message = format!(
+                "Could not generate a level, press {} to try again",
+                bindings.key_label(Action::GenerateLevel)
+            )
+        }
+    }
+    commands.remove_resource::<GeneratorTask>();
+}
+
+fn handle_file_dialog(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    keyboard_input: Res<Input<KeyCode>>,
+    mut actions: ResMut<Actions>,
+    mut character_reader: EventReader<ReceivedCharacter>,
+    mut editing_state: ResMut<EditingState>,
+    mut editor_files: ResMut<EditorFiles>,
+) {
+    let typed_characters: Vec<char> = character_reader.read().map(|event| event.char).collect();
+    let was_open = !matches!(editor_files.dialog, FileDialog::Closed);
+
+    editor_files.dialog = match std::mem::take(&mut editor_files.dialog) {
+        FileDialog::Closed if actions.just_pressed(Action::SaveLevel) => {
+            if editing_state.walls.is_empty() {
+                editor_files.message = "Nothing to save yet".to_string();
+                FileDialog::Closed
+            } else if let Err(message) = editing_state.playable_layout("saving") {
+                editor_files.message = message;
+                FileDialog::Closed
+            } else {
+                FileDialog::Save {
+                    name: editor_files.level_name.clone().unwrap_or_default(),
+                }
+            }
+        }
+        FileDialog::Closed if actions.just_pressed(Action::OpenLevel) => {
+            let names = list_user_levels();
+            if names.is_empty() {
+                editor_files.message = "No saved levels".to_string();
+                FileDialog::Closed
+            } else {
+                FileDialog::Open { names, selected: 0 }
+            }
+        }
+        FileDialog::Closed => FileDialog::Closed,
+        FileDialog::Save { .. } if actions.just_pressed(Action::Cancel) => FileDialog::Closed,
+        FileDialog::Save { name } if actions.just_pressed(Action::Confirm) => {
+            if name.is_empty() {
+                FileDialog::Save { name }
+            } else if user_level_path(&name).exists() {
+                FileDialog::ConfirmOverwrite { name }
+            } else {
+                editor_files.message = save_editing_state(&editing_state, &name);
+                editor_files.level_name = Some(name);
+                FileDialog::Closed
+            }
+        }
+        FileDialog::Save { mut name } => {
+            if keyboard_input.just_pressed(KeyCode::Back) {
+                name.pop();
+            }
+            name.extend(
+                typed_characters
+                    .into_iter()
+                    .filter(|c| c.is_alphanumeric() || *c == ' ' || *c == '-' || *c == '_'),
+            );
+            FileDialog::Save { name }
+        }
+        FileDialog::ConfirmOverwrite { name } if actions.just_pressed(Action::Confirm) => {
+            editor_files.message = save_editing_state(&editing_state, &name);
+            editor_files.level_name = Some(name);
+            FileDialog::Closed
+        }
+        FileDialog::ConfirmOverwrite { name } if actions.just_pressed(Action::Cancel) => {
+            FileDialog::Save { name }
+        }
+        dialog @ FileDialog::ConfirmOverwrite { .. } => dialog,
+        FileDialog::Open { .. } if actions.just_pressed(Action::Cancel) => FileDialog::Closed,
+        FileDialog::Open { names, selected } if actions.just_pressed(Action::Confirm) => {
+            let name = &names[selected];
+            match load_collection(&user_level_path(name)) {
+                Ok(collection) => {
+                    let level = EditorLevel::from_layout(&collection.levels[0].layout);
+                    editing_state.restore(&mut commands, &asset_server, &level);
+                    editor_files.message = format!("Opened {}", name);
+                    editor_files.level_name = Some(name.clone());
+                }
+                Err(error) => editor_files.message = format!("Could not open {}: {}", name, error),
+            }
+            FileDialog::Closed
+        }
+        FileDialog::Open {
+            names,
+            mut selected,
+        } => {
+            if actions.just_pressed(Action::MoveUp) {
+                selected = selected.saturating_sub(1);
+            } else if actions.just_pressed(Action::MoveDown) {
+                selected = (selected + 1).min(names.len() - 1);
+            }
+            FileDialog::Open { names, selected }
+        }
+    };
+
+    // A button that closed a dialog shouldn't also edit the level.
+    if was_open {
+        for action in Action::ALL {
+            actions.reset(action);
+        }
+    }
+}
+
+fn update_editor_text(
+    editor_files: Res<EditorFiles>,
+    bindings: Res<Bindings>,
+    mut text_query: Query<&mut Text, With<EditorText>>,
+) {
+    let Some(mut text) = text_query.iter_mut().next() else {
+        return;
+    };
+
+    text.sections[0].value = match &editor_files.dialog {
+        FileDialog::Closed => {
+            let controls: Vec<String> = [
+                (Action::PlaceFloor, "floor"),
+                (Action::PlaceBlock, "block"),
+                (Action::PlaceGoal, "goal"),
+                (Action::PlacePlayer, "player"),
+                (Action::Remove, "remove"),
+                (Action::SaveLevel, "save"),
+                (Action::OpenLevel, "open"),
+                (Action::Playtest, "playtest"),
+                (Action::CheckLevel, "check"),
+                (Action::GenerateLevel, "generate"),
+            ]
+            .into_iter()
+            .map(|(action, label)| format!("{} {}", bindings.key_label(action), label))
+            .collect();
+            format!("{}\n{}", controls.join(", "), editor_files.message)
+        }
+        FileDialog::Save { name } => format!(
+            "Save as: {}_\n{} to save, {} to cancel",
+            name,
+            bindings.key_label(Action::Confirm),
+            bindings.key_label(Action::Cancel)
+        ),
+        FileDialog::ConfirmOverwrite { name } => format!(
+            "{} already exists, overwrite it? ({} yes, {} no)",
+            name,
+            bindings.key_label(Action::Confirm),
+            bindings.key_label(Action::Cancel)
+        ),
+        FileDialog::Open { names, selected } => {
+            let mut listing = format!(
+                "Open level ({} to open, {} to cancel)\n",
+                bindings.key_label(Action::Confirm),
+                bindings.key_label(Action::Cancel)
+            );
+            for (index, name) in names.iter().enumerate() {
+                let marker = if index == *selected { ">" } else { " " };
+                listing.push_str(&format!("{} {}\n", marker, name));
+            }
+            listing
+        }
+    };
 }
 
 fn handle_edit_input(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
     time: Res<Time>,
-    keyboard_input: Res<Input<KeyCode>>,
+    actions: Res<Actions>,
     mut editing_state: ResMut<EditingState>,
     mut cursor_query: Query<(&mut Cursor, &mut Transform)>,
 ) {
@@ -119,25 +643,12 @@ fn handle_edit_input(
         return;
     };
 
-    if keyboard_input.pressed(KeyCode::E) {
-        dbg!(editing_state.serialize());
-    }
-
     if !cursor.action_timer.finished() {
         cursor.action_timer.tick(time.delta());
         return;
     }
 
-    let mut movement: Option<(i32, i32)> = None;
-    if keyboard_input.pressed(KeyCode::Up) {
-        movement = Some((0, -1));
-    } else if keyboard_input.pressed(KeyCode::Down) {
-        movement = Some((0, 1));
-    } else if keyboard_input.pressed(KeyCode::Left) {
-        movement = Some((-1, 0));
-    } else if keyboard_input.pressed(KeyCode::Right) {
-        movement = Some((1, 0));
-    }
+    let movement = actions.direction().map(Direction::offset);
 
     let mut cursor_position = Position::from_translation(transform.translation);
 
@@ -148,7 +659,7 @@ fn handle_edit_input(
         transform.translation = cursor_position.to_translation_z(2.0);
     }
 
-    if keyboard_input.pressed(KeyCode::Z) && !editing_state.floors.contains_key(&cursor_position) {
+    if actions.pressed(Action::PlaceFloor) && !editing_state.floors.contains_key(&cursor_position) {
         cursor.action_timer.reset();
 
         let floor_entity = commands
@@ -179,75 +690,41 @@ fn handle_edit_input(
                 && !editing_state.walls.contains_key(&wall_position)
             {
                 let wall_id = commands
-                    .spawn(SpriteBundle {
-                        sprite: Sprite {
-                            anchor: Anchor::TopLeft,
-                            ..default()
-                        },
-                        texture: asset_server.load("wall.png"),
-                        transform: Transform::from_translation(wall_position.to_translation()),
-                        ..default()
-                    })
+                    .spawn(spawn_wall(&asset_server, wall_position))
                     .id();
                 editing_state.walls.insert(wall_position, wall_id);
             }
         }
-    } else if keyboard_input.pressed(KeyCode::X) && editing_state.can_place(&cursor_position) {
+    } else if actions.pressed(Action::PlaceBlock)
+        && editing_state.can_place_object(&cursor_position)
+    {
         cursor.action_timer.reset();
 
-        let block_translation = cursor_position.to_translation();
-
         let block_id = commands
-            .spawn(SpriteBundle {
-                sprite: Sprite {
-                    anchor: Anchor::TopLeft,
-                    ..default()
-                },
-                texture: asset_server.load("block.png"),
-                transform: Transform::from_translation(block_translation),
-                ..default()
-            })
+            .spawn(spawn_block(&asset_server, cursor_position))
             .id();
         editing_state.blocks.insert(cursor_position, block_id);
-    } else if keyboard_input.pressed(KeyCode::C) && editing_state.can_place(&cursor_position) {
+    } else if actions.pressed(Action::PlaceGoal) && editing_state.can_place_goal(&cursor_position) {
         cursor.action_timer.reset();
 
-        let goal_translation = cursor_position.to_translation_z(0.5);
-
         let goal_id = commands
-            .spawn(SpriteBundle {
-                sprite: Sprite {
-                    anchor: Anchor::TopLeft,
-                    ..default()
-                },
-                texture: asset_server.load("goal.png"),
-                transform: Transform::from_translation(goal_translation),
-                ..default()
-            })
+            .spawn(spawn_goal(&asset_server, cursor_position))
             .id();
         editing_state.goals.insert(cursor_position, goal_id);
-    } else if keyboard_input.pressed(KeyCode::V) && editing_state.can_place(&cursor_position) {
+    } else if actions.pressed(Action::PlacePlayer)
+        && editing_state.can_place_object(&cursor_position)
+    {
         cursor.action_timer.reset();
 
-        let player_translation = cursor_position.to_translation();
-
         let player_id = commands
-            .spawn(SpriteBundle {
-                sprite: Sprite {
-                    anchor: Anchor::TopLeft,
-                    ..default()
-                },
-                texture: asset_server.load("player.png"),
-                transform: Transform::from_translation(player_translation),
-                ..default()
-            })
+            .spawn(spawn_player(&asset_server, cursor_position))
             .id();
 
-        if editing_state.player.is_some() {
-            commands.entity(editing_state.player.unwrap().1).despawn();
+        if let Some((_, previous_player_id)) = editing_state.player {
+            commands.entity(previous_player_id).despawn();
         }
         editing_state.player = Some((cursor_position, player_id));
-    } else if keyboard_input.pressed(KeyCode::S) {
+    } else if actions.pressed(Action::Remove) {
         let Some(removed_entity) = editing_state.remove_object(&cursor_position) else {
             return;
         };
@@ -261,7 +738,25 @@ impl Plugin for EditPlugin {
         app.add_systems(OnEnter(GameState::Editing), (remove_level, show_cursor))
             .add_systems(
                 Update,
-                handle_edit_input.run_if(in_state(GameState::Editing)),
+                (
+                    handle_file_dialog,
+                    handle_edit_input
+                        .run_if(file_dialog_closed)
+                        .after(handle_file_dialog),
+                    start_playtest
+                        .run_if(file_dialog_closed)
+                        .after(handle_file_dialog),
+                    check_solvable
+                        .run_if(file_dialog_closed)
+                        .after(handle_file_dialog),
+                    report_solution,
+                    start_generating
+                        .run_if(file_dialog_closed)
+                        .after(handle_file_dialog),
+                    place_generated_level,
+                    update_editor_text.after(handle_file_dialog),
+                )
+                    .run_if(in_state(GameState::Editing)),
             );
     }
 }
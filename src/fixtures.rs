@@ -0,0 +1,19 @@
+use crate::{
+    cell::{Cell, Layout},
+    solver::Puzzle,
+};
+
+// Builds a layout from rows written like an XSB level.
+pub(crate) fn layout(rows: &[&str]) -> Layout {
+    rows.iter()
+        .map(|row| {
+            row.chars()
+                .map(|glyph| Cell::from_glyph(glyph).unwrap())
+                .collect()
+        })
+        .collect()
+}
+
+pub(crate) fn puzzle(rows: &[&str]) -> Puzzle {
+    Puzzle::from_layout(&layout(rows)).unwrap()
+}
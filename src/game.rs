@@ -0,0 +1,261 @@
+use crate::{cell::Layout, solver::Puzzle, Direction, Position};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MoveResult {
+    Moved,
+    Pushed,
+    HitWall,
+    BlockStuck,
+}
+
+impl MoveResult {
+    pub fn is_legal(self) -> bool {
+        matches!(self, MoveResult::Moved | MoveResult::Pushed)
+    }
+}
+
+// The rules of Sokoban, with every move kept in LURD notation so it can be undone.
+pub struct Game {
+    puzzle: Puzzle,
+    moves: usize,
+    pushes: usize,
+    lurd: String,
+    // The next move to redo is last.
+    redo: String,
+}
+
+impl Game {
+    pub fn new(puzzle: Puzzle) -> Game {
+        Game {
+            puzzle,
+            moves: 0,
+            pushes: 0,
+            lurd: String::new(),
+            redo: String::new(),
+        }
+    }
+
+    pub fn from_layout(layout: &Layout) -> Option<Game> {
+        Puzzle::from_layout(layout).map(Game::new)
+    }
+
+    pub fn puzzle(&self) -> &Puzzle {
+        &self.puzzle
+    }
+
+    pub fn player(&self) -> Position {
+        self.puzzle.player
+    }
+
+    pub fn moves(&self) -> usize {
+        self.moves
+    }
+
+    pub fn pushes(&self) -> usize {
+        self.pushes
+    }
+
+    pub fn lurd(&self) -> &str {
+        &self.lurd
+    }
+
+    pub fn check_move(&self, direction: Direction) -> MoveResult {
+        let move_to = self.puzzle.player.step(direction);
+        if self.puzzle.walls.contains(&move_to) {
+            return MoveResult::HitWall;
+        }
+        if !self.puzzle.blocks.contains(&move_to) {
+            return MoveResult::Moved;
+        }
+
+        let block_move_to = move_to.step(direction);
+        if self.puzzle.walls.contains(&block_move_to) || self.puzzle.blocks.contains(&block_move_to)
+        {
+            MoveResult::BlockStuck
+        } else {
+            MoveResult::Pushed
+        }
+    }
+
+    pub fn try_move(&mut self, direction: Direction) -> MoveResult {
+        let result = self.check_move(direction);
+        if !result.is_legal() {
+            return result;
+        }
+
+        let move_to = self.puzzle.player.step(direction);
+        let is_push = result == MoveResult::Pushed;
+        if is_push {
+            self.puzzle.blocks.remove(&move_to);
+            self.puzzle.blocks.insert(move_to.step(direction));
+            self.pushes += 1;
+        }
+        self.puzzle.player = move_to;
+        self.moves += 1;
+
+        // Only making the move that was undone last keeps the redo history.
+        let lurd = direction.to_lurd(is_push);
+        if self.redo.ends_with(lurd) {
+            self.redo.pop();
+        } else {
+            self.redo.clear();
+        }
+        self.lurd.push(lurd);
+        result
+    }
+
+    pub fn undo(&mut self) -> Option<(Direction, bool)> {
+        let lurd = self.lurd.pop()?;
+        let (direction, is_push) = Direction::from_lurd(lurd)?;
+        self.redo.push(lurd);
+
+        let player = self.puzzle.player;
+        if is_push {
+            self.puzzle.blocks.remove(&player.step(direction));
+            self.puzzle.blocks.insert(player);
+            self.pushes -= 1;
+        }
+        self.puzzle.player = player.step(direction.opposite());
+        self.moves -= 1;
+        Some((direction, is_push))
+    }
+
+    pub fn redo_direction(&self) -> Option<Direction> {
+        self.redo_directions().next()
+    }
+
+    pub fn redo_directions(&self) -> impl Iterator<Item = Direction> + '_ {
+        self.redo
+            .chars()
+            .rev()
+            .filter_map(|lurd| Direction::from_lurd(lurd).map(|(direction, _)| direction))
+    }
+
+    pub fn is_won(&self) -> bool {
+        self.puzzle
+            .goals
+            .iter()
+            .all(|goal| self.puzzle.blocks.contains(goal))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::puzzle;
+
+    fn game(rows: &[&str]) -> Game {
+        Game::new(puzzle(rows))
+    }
+
+    #[test]
+    fn walks_onto_floor() {
+        let mut game = game(&["#####", "#@  #", "#####"]);
+        assert_eq!(game.try_move(Direction::Right), MoveResult::Moved);
+        assert_eq!(game.player(), Position { x: 2, y: 1 });
+        assert_eq!(game.lurd(), "r");
+    }
+
+    #[test]
+    fn stops_at_walls() {
+        let mut game = game(&["#####", "#@  #", "#####"]);
+        assert_eq!(game.try_move(Direction::Left), MoveResult::HitWall);
+        assert_eq!(game.try_move(Direction::Up), MoveResult::HitWall);
+        assert_eq!(game.player(), Position { x: 1, y: 1 });
+        assert_eq!(game.moves(), 0);
+        assert_eq!(game.lurd(), "");
+    }
+
+    #[test]
+    fn pushes_a_block() {
+        let mut game = game(&["######", "#@$ .#", "######"]);
+        assert_eq!(game.try_move(Direction::Right), MoveResult::Pushed);
+        assert_eq!(game.player(), Position { x: 2, y: 1 });
+        assert!(game.puzzle().blocks.contains(&Position { x: 3, y: 1 }));
+        assert!(!game.puzzle().blocks.contains(&Position { x: 2, y: 1 }));
+        assert_eq!(game.lurd(), "R");
+    }
+
+    #[test]
+    fn cannot_push_a_block_into_another_block() {
+        let mut game = game(&["######", "#@$$ #", "######"]);
+        assert_eq!(game.try_move(Direction::Right), MoveResult::BlockStuck);
+        assert_eq!(game.player(), Position { x: 1, y: 1 });
+        assert!(game.puzzle().blocks.contains(&Position { x: 2, y: 1 }));
+        assert!(game.puzzle().blocks.contains(&Position { x: 3, y: 1 }));
+        assert_eq!(game.lurd(), "");
+    }
+
+    #[test]
+    fn cannot_push_a_block_into_a_wall() {
+        let mut game = game(&["####", "#@$#", "####"]);
+        assert_eq!(game.try_move(Direction::Right), MoveResult::BlockStuck);
+        assert_eq!(game.player(), Position { x: 1, y: 1 });
+        assert!(game.puzzle().blocks.contains(&Position { x: 2, y: 1 }));
+    }
+
+    #[test]
+    fn undo_takes_back_pushes_and_keeps_them_for_redo() {
+        let mut game = game(&["#######", "#@$  .#", "#######"]);
+        game.try_move(Direction::Right);
+        game.try_move(Direction::Right);
+        assert_eq!(game.redo_direction(), None);
+
+        assert_eq!(game.undo(), Some((Direction::Right, true)));
+        assert_eq!(game.player(), Position { x: 2, y: 1 });
+        assert!(game.puzzle().blocks.contains(&Position { x: 3, y: 1 }));
+        assert_eq!(game.redo_direction(), Some(Direction::Right));
+
+        assert_eq!(game.undo(), Some((Direction::Right, true)));
+        assert_eq!(game.player(), Position { x: 1, y: 1 });
+        assert!(game.puzzle().blocks.contains(&Position { x: 2, y: 1 }));
+        assert_eq!((game.moves(), game.pushes(), game.lurd()), (0, 0, ""));
+        assert_eq!(game.undo(), None);
+
+        // Redoing one move keeps the other, while a different move forgets it.
+        assert_eq!(game.try_move(Direction::Right), MoveResult::Pushed);
+        assert_eq!(game.redo_direction(), Some(Direction::Right));
+        assert_eq!(game.try_move(Direction::Left), MoveResult::Moved);
+        assert_eq!(game.redo_direction(), None);
+    }
+
+    #[test]
+    fn redo_directions_play_back_the_undone_moves_in_order() {
+        let mut game = game(&["######", "#@   #", "#    #", "######"]);
+        for direction in [Direction::Right, Direction::Down, Direction::Right] {
+            game.try_move(direction);
+        }
+        game.undo();
+        game.undo();
+        let redo: Vec<Direction> = game.redo_directions().collect();
+        assert_eq!(redo, [Direction::Down, Direction::Right]);
+    }
+
+    #[test]
+    fn is_won_once_every_goal_is_covered() {
+        let mut game = game(&["######", "#@$.$#", "######"]);
+        assert!(!game.is_won());
+        game.try_move(Direction::Right);
+        assert!(game.is_won());
+        game.undo();
+        assert!(!game.is_won());
+    }
+
+    #[test]
+    fn counts_moves_and_pushes() {
+        let mut game = game(&["#######", "#     #", "#@$  .#", "#######"]);
+        game.try_move(Direction::Right);
+        game.try_move(Direction::Right);
+        game.try_move(Direction::Up);
+        game.try_move(Direction::Left);
+        game.try_move(Direction::Down);
+        assert_eq!(game.moves(), 5);
+        assert_eq!(game.pushes(), 2);
+        assert_eq!(game.lurd(), "RRuld");
+
+        game.try_move(Direction::Down);
+        assert_eq!(game.moves(), 5);
+        game.undo();
+        assert_eq!((game.moves(), game.pushes()), (4, 2));
+    }
+}
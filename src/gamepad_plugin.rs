@@ -0,0 +1,100 @@
+use bevy::{
+    input::{gamepad::GamepadSettings, InputSystem},
+    prelude::*,
+};
+use bevy_sokoban::Direction;
+
+pub struct GamepadPlugin;
+
+const STICK_DEAD_ZONE: f32 = 0.4;
+
+// Every connected gamepad merged into one. The stick always moves, buttons are bound like keys.
+#[derive(Resource, Default)]
+pub struct GamepadInput {
+    pub stick: Option<Direction>,
+    pub stick_just_moved: bool,
+    pub buttons: Input<GamepadButtonType>,
+}
+
+pub const BUTTONS: [GamepadButtonType; 16] = [
+    GamepadButtonType::South,
+    GamepadButtonType::East,
+    GamepadButtonType::North,
+    GamepadButtonType::West,
+    GamepadButtonType::LeftTrigger,
+    GamepadButtonType::LeftTrigger2,
+    GamepadButtonType::RightTrigger,
+    GamepadButtonType::RightTrigger2,
+    GamepadButtonType::Select,
+    GamepadButtonType::Start,
+    GamepadButtonType::LeftThumb,
+    GamepadButtonType::RightThumb,
+    GamepadButtonType::DPadUp,
+    GamepadButtonType::DPadDown,
+    GamepadButtonType::DPadLeft,
+    GamepadButtonType::DPadRight,
+];
+
+fn set_dead_zones(mut gamepad_settings: ResMut<GamepadSettings>) {
+    let axis_settings = &mut gamepad_settings.default_axis_settings;
+    axis_settings.set_deadzone_upperbound(STICK_DEAD_ZONE);
+    axis_settings.set_deadzone_lowerbound(-STICK_DEAD_ZONE);
+}
+
+// A diagonal stick goes whichever way it leans most.
+fn read_stick(gamepad: Gamepad, axes: &Axis<GamepadAxis>) -> Option<Direction> {
+    let x = axes
+        .get(GamepadAxis::new(gamepad, GamepadAxisType::LeftStickX))
+        .unwrap_or(0.0);
+    let y = axes
+        .get(GamepadAxis::new(gamepad, GamepadAxisType::LeftStickY))
+        .unwrap_or(0.0);
+    if x == 0.0 && y == 0.0 {
+        None
+    } else if x.abs() > y.abs() {
+        Some(if x > 0.0 {
+            Direction::Right
+        } else {
+            Direction::Left
+        })
+    } else {
+        Some(if y > 0.0 {
+            Direction::Up
+        } else {
+            Direction::Down
+        })
+    }
+}
+
+pub fn read_gamepads(
+    gamepads: Res<Gamepads>,
+    buttons: Res<Input<GamepadButton>>,
+    axes: Res<Axis<GamepadAxis>>,
+    mut gamepad_input: ResMut<GamepadInput>,
+) {
+    let stick = gamepads
+        .iter()
+        .find_map(|gamepad| read_stick(gamepad, &axes));
+    gamepad_input.stick_just_moved = stick.is_some() && stick != gamepad_input.stick;
+    gamepad_input.stick = stick;
+
+    gamepad_input.buttons.clear();
+    for button_type in BUTTONS {
+        let is_pressed = gamepads
+            .iter()
+            .any(|gamepad| buttons.pressed(GamepadButton::new(gamepad, button_type)));
+        if is_pressed {
+            gamepad_input.buttons.press(button_type);
+        } else {
+            gamepad_input.buttons.release(button_type);
+        }
+    }
+}
+
+impl Plugin for GamepadPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(GamepadInput::default())
+            .add_systems(Startup, set_dead_zones)
+            .add_systems(PreUpdate, read_gamepads.after(InputSystem));
+    }
+}
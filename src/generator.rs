@@ -0,0 +1,335 @@
+use std::{collections::HashSet, time::Duration};
+
+use crate::{
+    cell::{Cell, Layout},
+    solver::{solve, Puzzle, SolverConfig},
+    Direction, Position,
+};
+
+// Rooms are stamped together from these 3x3 pieces, each turned and mirrored at random.
+const TEMPLATES: [[&str; 3]; 10] = [
+    ["   ", "   ", "   "],
+    ["#  ", "   ", "   "],
+    ["## ", "   ", "   "],
+    ["###", "   ", "   "],
+    ["#  ", "#  ", "   "],
+    [" # ", "   ", "   "],
+    ["   ", " # ", "   "],
+    ["#  ", "   ", "  #"],
+    ["## ", "#  ", "   "],
+    ["# #", "   ", "   "],
+];
+
+#[derive(Clone, Debug)]
+pub struct GeneratorConfig {
+    pub width: usize,
+    pub height: usize,
+    pub blocks: usize,
+    // Pushes the best solution should take. Levels needing fewer than half as many are rejected.
+    pub difficulty: usize,
+    pub seed: u64,
+    pub attempts: usize,
+}
+
+impl Default for GeneratorConfig {
+    fn default() -> Self {
+        Self {
+            width: 9,
+            height: 9,
+            blocks: 3,
+            difficulty: 12,
+            seed: 0,
+            attempts: 200,
+        }
+    }
+}
+
+// SplitMix64, seeded so a level can be made again.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    fn below(&mut self, bound: usize) -> usize {
+        (self.next() % bound as u64) as usize
+    }
+
+    fn choose<T: Copy>(&mut self, items: &[T]) -> Option<T> {
+        if items.is_empty() {
+            None
+        } else {
+            Some(items[self.below(items.len())])
+        }
+    }
+}
+
+// Keeps the attempt whose solution comes closest to the requested number of pushes.
+pub fn generate(config: &GeneratorConfig) -> Option<Layout> {
+    let mut rng = Rng(config.seed);
+    let solver_config = SolverConfig {
+        max_nodes: 100_000,
+        time_limit: Some(Duration::from_secs(2)),
+    };
+
+    let mut best: Option<(usize, Puzzle)> = None;
+    for _ in 0..config.attempts {
+        let Some(floor) = build_room(&mut rng, config) else {
+            continue;
+        };
+        let Some(puzzle) = scatter_blocks(&mut rng, &floor, config) else {
+            continue;
+        };
+        let Ok(solution) = solve(&puzzle, &solver_config) else {
+            continue;
+        };
+        if solution.pushes * 2 < config.difficulty || solution.pushes < 2 * config.blocks {
+            continue;
+        }
+
+        let distance = solution.pushes.abs_diff(config.difficulty);
+        let is_closer = match &best {
+            Some((best_distance, _)) => distance < *best_distance,
+            None => true,
+        };
+        if is_closer {
+            best = Some((distance, puzzle));
+        }
+        if distance <= config.difficulty / 5 {
+            break;
+        }
+    }
+
+    best.map(|(_, puzzle)| to_layout(&puzzle))
+}
+
+fn template_is_wall(
+    template: &[&str; 3],
+    x: usize,
+    y: usize,
+    turns: usize,
+    mirrored: bool,
+) -> bool {
+    let (mut x, mut y) = (x, y);
+    if mirrored {
+        x = 2 - x;
+    }
+    for _ in 0..turns {
+        (x, y) = (2 - y, x);
+    }
+    template[y].as_bytes()[x] == b'#'
+}
+
+fn build_room(rng: &mut Rng, config: &GeneratorConfig) -> Option<HashSet<Position>> {
+    let inner_width = config.width.checked_sub(2)?;
+    let inner_height = config.height.checked_sub(2)?;
+
+    let mut floor = HashSet::new();
+    for tile_y in (0..inner_height).step_by(3) {
+        for tile_x in (0..inner_width).step_by(3) {
+            let template = &TEMPLATES[rng.below(TEMPLATES.len())];
+            let turns = rng.below(4);
+            let mirrored = rng.below(2) == 1;
+            for y in 0..3 {
+                for x in 0..3 {
+                    if tile_x + x >= inner_width || tile_y + y >= inner_height {
+                        continue;
+                    }
+                    if !template_is_wall(template, x, y, turns, mirrored) {
+                        floor.insert(Position {
+                            x: (tile_x + x + 1) as i32,
+                            y: (tile_y + y + 1) as i32,
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    let mut largest = HashSet::new();
+    let mut unvisited = floor.clone();
+    for start in sorted(floor) {
+        if !unvisited.contains(&start) {
+            continue;
+        }
+        let mut region = HashSet::new();
+        let mut to_visit = vec![start];
+        while let Some(position) = to_visit.pop() {
+            if !unvisited.remove(&position) {
+                continue;
+            }
+            region.insert(position);
+            to_visit.extend(Direction::ALL.map(|direction| position.step(direction)));
+        }
+        if region.len() > largest.len() {
+            largest = region;
+        }
+    }
+
+    if largest.len() < config.blocks * 3 + 4 {
+        return None;
+    }
+    Some(largest)
+}
+
+fn reachable_cells(
+    player: Position,
+    floor: &HashSet<Position>,
+    blocks: &HashSet<Position>,
+) -> HashSet<Position> {
+    let mut visited = HashSet::new();
+    let mut to_visit = vec![player];
+    while let Some(position) = to_visit.pop() {
+        if !floor.contains(&position) || blocks.contains(&position) || !visited.insert(position) {
+            continue;
+        }
+        to_visit.extend(Direction::ALL.map(|direction| position.step(direction)));
+    }
+    visited
+}
+
+fn sorted(positions: impl IntoIterator<Item = Position>) -> Vec<Position> {
+    let mut positions: Vec<Position> = positions.into_iter().collect();
+    positions.sort_by_key(|position| (position.y, position.x));
+    positions
+}
+
+// Pulls blocks off their goals, so the result can always be pushed back.
+fn scatter_blocks(
+    rng: &mut Rng,
+    floor: &HashSet<Position>,
+    config: &GeneratorConfig,
+) -> Option<Puzzle> {
+    let mut cells = sorted(floor.iter().copied());
+    let mut goals = HashSet::new();
+    while goals.len() < config.blocks {
+        goals.insert(cells.swap_remove(rng.below(cells.len())));
+    }
+    let mut blocks = goals.clone();
+    let mut player = rng.choose(&cells)?;
+
+    for _ in 0..config.difficulty * 4 {
+        let reachable = reachable_cells(player, floor, &blocks);
+        let mut pulls = Vec::new();
+        for block in sorted(blocks.iter().copied()) {
+            for direction in Direction::ALL {
+                let block_to = block.step(direction);
+                let player_to = block_to.step(direction);
+                if reachable.contains(&block_to) && reachable.contains(&player_to) {
+                    pulls.push((block, block_to, player_to));
+                }
+            }
+        }
+        let Some((block, block_to, player_to)) = rng.choose(&pulls) else {
+            break;
+        };
+        blocks.remove(&block);
+        blocks.insert(block_to);
+        player = player_to;
+    }
+
+    if blocks.iter().any(|block| goals.contains(block)) {
+        return None;
+    }
+    let reachable = sorted(reachable_cells(player, floor, &blocks));
+    let player = rng.choose(&reachable)?;
+
+    let walls = floor
+        .iter()
+        .flat_map(|position| {
+            [
+                (-1, -1),
+                (0, -1),
+                (1, -1),
+                (-1, 0),
+                (1, 0),
+                (-1, 1),
+                (0, 1),
+                (1, 1),
+            ]
+            .map(|(x, y)| position.add(x, y))
+        })
+        .filter(|position| !floor.contains(position))
+        .collect();
+    Some(Puzzle {
+        walls,
+        goals,
+        blocks,
+        player,
+    })
+}
+
+fn to_layout(puzzle: &Puzzle) -> Layout {
+    let min_x = puzzle.walls.iter().map(|wall| wall.x).min().unwrap_or(0);
+    let min_y = puzzle.walls.iter().map(|wall| wall.y).min().unwrap_or(0);
+    let max_x = puzzle.walls.iter().map(|wall| wall.x).max().unwrap_or(0);
+    let max_y = puzzle.walls.iter().map(|wall| wall.y).max().unwrap_or(0);
+
+    let mut layout =
+        vec![vec![Cell::EMPTY; (max_x - min_x + 1) as usize]; (max_y - min_y + 1) as usize];
+    for y in min_y..=max_y {
+        for x in min_x..=max_x {
+            let position = Position { x, y };
+            let cell = &mut layout[(y - min_y) as usize][(x - min_x) as usize];
+            if puzzle.walls.contains(&position) {
+                *cell |= Cell::WALL;
+            }
+            if puzzle.goals.contains(&position) {
+                *cell |= Cell::GOAL;
+            }
+            if puzzle.blocks.contains(&position) {
+                *cell |= Cell::BLOCK;
+            }
+            if puzzle.player == position {
+                *cell |= Cell::PLAYER;
+            }
+        }
+    }
+    layout
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::levels::{is_enclosed, to_xsb};
+
+    fn config(seed: u64) -> GeneratorConfig {
+        GeneratorConfig {
+            width: 7,
+            height: 7,
+            blocks: 2,
+            difficulty: 6,
+            seed,
+            ..GeneratorConfig::default()
+        }
+    }
+
+    #[test]
+    fn generates_solvable_enclosed_levels() {
+        for seed in 1..=3 {
+            let layout = generate(&config(seed)).unwrap();
+            assert!(is_enclosed(&layout));
+
+            let puzzle = Puzzle::from_layout(&layout).unwrap();
+            assert_eq!(puzzle.blocks.len(), 2);
+            assert_eq!(puzzle.goals.len(), 2);
+            assert!(puzzle
+                .blocks
+                .iter()
+                .all(|block| !puzzle.goals.contains(block)));
+            assert!(solve(&puzzle, &SolverConfig::default()).is_ok());
+        }
+    }
+
+    #[test]
+    fn the_same_seed_makes_the_same_level() {
+        let first = generate(&config(7)).unwrap();
+        let second = generate(&config(7)).unwrap();
+        assert_eq!(to_xsb(&first), to_xsb(&second));
+    }
+}
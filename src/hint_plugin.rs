@@ -0,0 +1,170 @@
+use bevy::{
+    prelude::*,
+    tasks::{block_on, AsyncComputeTaskPool, Task},
+};
+
+use bevy_sokoban::{
+    solver::{solve, SolveError, SolverConfig},
+    Direction, Position,
+};
+
+use crate::{
+    action_plugin::{Action, Actions, Bindings},
+    play_plugin::{LevelState, Player},
+    tiles::spawn_block,
+    GameState,
+};
+
+pub struct HintPlugin;
+
+pub const HINT_COLOR: Color = Color::YELLOW;
+
+type NextPush = Result<Option<(Position, Direction)>, SolveError>;
+
+#[derive(Resource, Default)]
+pub struct Hint {
+    task: Option<Task<NextPush>>,
+    pub highlighted_block: Option<Entity>,
+}
+
+#[derive(Component)]
+struct HintMarker;
+
+fn hint_text(message: &str) -> impl Bundle {
+    (
+        HintMarker,
+        TextBundle::from_section(
+            message,
+            TextStyle {
+                font_size: 14.0,
+                color: Color::WHITE,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(5.0),
+            left: Val::Px(5.0),
+            ..default()
+        }),
+    )
+}
+
+fn clear_hint(
+    mut commands: Commands,
+    level_state: Res<LevelState>,
+    mut hint: ResMut<Hint>,
+    marker_query: Query<Entity, With<HintMarker>>,
+    mut sprite_query: Query<&mut Sprite>,
+) {
+    if !level_state.is_changed() {
+        return;
+    }
+
+    // Leave a deadlock tint alone.
+    hint.task = None;
+    if let Some(block_entity) = hint.highlighted_block.take() {
+        if let Ok(mut sprite) = sprite_query.get_mut(block_entity) {
+            if sprite.color == HINT_COLOR {
+                sprite.color = Color::WHITE;
+            }
+        }
+    }
+    for entity in marker_query.iter() {
+        commands.entity(entity).despawn();
+    }
+}
+
+fn request_hint(
+    mut commands: Commands,
+    actions: Res<Actions>,
+    level_state: Res<LevelState>,
+    mut hint: ResMut<Hint>,
+    player_query: Query<&Player>,
+    marker_query: Query<Entity, With<HintMarker>>,
+) {
+    if !actions.just_pressed(Action::Hint) || hint.task.is_some() {
+        return;
+    }
+    if player_query.iter().any(|player| player.is_moving) {
+        return;
+    }
+
+    for entity in marker_query.iter() {
+        commands.entity(entity).despawn();
+    }
+    commands.spawn(hint_text("Thinking..."));
+
+    let puzzle = level_state.game().puzzle().clone();
+    hint.task = Some(AsyncComputeTaskPool::get().spawn(async move {
+        solve(&puzzle, &SolverConfig::default()).map(|solution| puzzle.first_push(&solution.moves))
+    }));
+}
+
+fn show_hint(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    level_state: Res<LevelState>,
+    bindings: Res<Bindings>,
+    mut hint: ResMut<Hint>,
+    marker_query: Query<Entity, With<HintMarker>>,
+    mut sprite_query: Query<&mut Sprite>,
+) {
+    let Some(task) = hint.task.as_mut() else {
+        return;
+    };
+    if !task.is_finished() {
+        return;
+    }
+    let next_push = block_on(task);
+    hint.task = None;
+
+    for entity in marker_query.iter() {
+        commands.entity(entity).despawn();
+    }
+
+    let (block_position, direction) = match next_push {
+        Ok(Some(push)) => push,
+        Ok(None) => return,
+        Err(SolveError::Unsolvable) => {
+            commands.spawn(hint_text(&format!(
+                "No solution from this position, press {} to undo",
+                bindings.key_label(Action::Undo)
+            )));
+            return;
+        }
+        Err(SolveError::BudgetExceeded { .. }) => {
+            commands.spawn(hint_text("Could not find a solution in time"));
+            return;
+        }
+    };
+
+    if let Some(block_entity) = level_state.blocks.get(&block_position) {
+        if let Ok(mut sprite) = sprite_query.get_mut(*block_entity) {
+            sprite.color = HINT_COLOR;
+        }
+        hint.highlighted_block = Some(*block_entity);
+    }
+
+    let mut target = spawn_block(&asset_server, block_position.step(direction));
+    target.sprite.color = Color::rgba(1.0, 1.0, 0.0, 0.4);
+    commands.spawn((HintMarker, target));
+    commands.spawn(hint_text(&format!(
+        "Push the highlighted block {}",
+        format!("{:?}", direction).to_lowercase()
+    )));
+}
+
+impl Plugin for HintPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Hint::default()).add_systems(
+            Update,
+            (
+                clear_hint,
+                request_hint.after(clear_hint),
+                show_hint.after(request_hint),
+            )
+                .run_if(in_state(GameState::Playing)),
+        );
+    }
+}
@@ -0,0 +1,264 @@
+use std::{collections::VecDeque, ops::Range};
+
+use crate::{
+    game::{Game, MoveResult},
+    Direction,
+};
+
+// A game played a move at a time, where a walked path or a restart is undone as one action.
+pub struct History {
+    game: Game,
+    walk: VecDeque<Direction>,
+    walk_start: Option<usize>,
+    // Stretches of the moves made by walking a path.
+    walks: Vec<Range<usize>>,
+    undone_walks: Vec<Range<usize>>,
+    // How many moves the last restart took back, until another move is made.
+    restarted: Option<usize>,
+}
+
+impl History {
+    pub fn new(game: Game) -> History {
+        History {
+            game,
+            walk: VecDeque::new(),
+            walk_start: None,
+            walks: Vec::new(),
+            undone_walks: Vec::new(),
+            restarted: None,
+        }
+    }
+
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    pub fn try_move(&mut self, direction: Direction) -> MoveResult {
+        let is_redo = self.game.redo_direction() == Some(direction);
+        let result = self.game.try_move(direction);
+        if result.is_legal() {
+            self.restarted = None;
+        }
+        if result.is_legal() && !is_redo {
+            self.undone_walks.clear();
+        }
+        result
+    }
+
+    pub fn undo(&mut self) -> bool {
+        self.end_walk();
+        if let Some(length) = self.restarted.take() {
+            self.undo_restart(length);
+            return true;
+        }
+
+        let history = self.game.lurd().len();
+        let start = match self.walks.last() {
+            Some(walk) if walk.end == history => {
+                let start = walk.start;
+                self.undone_walks.extend(self.walks.pop());
+                start
+            }
+            _ => history.saturating_sub(1),
+        };
+
+        let mut undone = false;
+        while self.game.lurd().len() > start && self.game.undo().is_some() {
+            undone = true;
+        }
+        undone
+    }
+
+    pub fn restart(&mut self) -> bool {
+        self.end_walk();
+        let length = self.game.lurd().len();
+        if length == 0 {
+            return false;
+        }
+        while self.undo() {}
+        self.restarted = Some(length);
+        true
+    }
+
+    fn undo_restart(&mut self, length: usize) {
+        let moves: Vec<Direction> = self.game.redo_directions().take(length).collect();
+        for direction in moves {
+            self.game.try_move(direction);
+        }
+        while self
+            .undone_walks
+            .last()
+            .is_some_and(|walk| walk.end <= length)
+        {
+            self.walks.extend(self.undone_walks.pop());
+        }
+    }
+
+    // Starts walking the next undone walk again, if redoing has reached it.
+    pub fn redo_walk(&mut self) -> bool {
+        let history = self.game.lurd().len();
+        let length = match self.undone_walks.last() {
+            Some(walk) if walk.start == history => walk.len(),
+            _ => return false,
+        };
+        self.undone_walks.pop();
+        let moves = self.game.redo_directions().take(length).collect();
+        self.start_walk(moves);
+        true
+    }
+
+    pub fn start_walk(&mut self, moves: Vec<Direction>) {
+        self.end_walk();
+        self.walk = moves.into();
+        self.walk_start = Some(self.game.lurd().len());
+    }
+
+    pub fn is_walking(&self) -> bool {
+        self.walk_start.is_some()
+    }
+
+    // The walk ends once the move being made is done.
+    pub fn stop_walk(&mut self) {
+        self.walk.clear();
+    }
+
+    pub fn next_walk_step(&mut self) -> Option<Direction> {
+        let step = self.walk.pop_front();
+        if step.is_none() {
+            self.end_walk();
+        }
+        step
+    }
+
+    pub fn end_walk(&mut self) {
+        self.walk.clear();
+        let Some(start) = self.walk_start.take() else {
+            return;
+        };
+        let end = self.game.lurd().len();
+        if end > start {
+            self.walks.push(start..end);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fixtures::puzzle, Position};
+
+    fn history(rows: &[&str]) -> History {
+        History::new(Game::new(puzzle(rows)))
+    }
+
+    fn walk(history: &mut History, moves: &[Direction]) {
+        history.start_walk(moves.to_vec());
+        while let Some(direction) = history.next_walk_step() {
+            assert!(history.try_move(direction).is_legal());
+        }
+    }
+
+    fn redo(history: &mut History) {
+        assert!(history.redo_walk());
+        while let Some(direction) = history.next_walk_step() {
+            assert!(history.try_move(direction).is_legal());
+        }
+    }
+
+    #[test]
+    fn undoes_a_walk_as_one_action() {
+        let mut history = history(&["#######", "#@    #", "#     #", "#######"]);
+        walk(
+            &mut history,
+            &[Direction::Right, Direction::Right, Direction::Right],
+        );
+        history.try_move(Direction::Down);
+
+        assert!(history.undo());
+        assert_eq!(history.game().lurd(), "rrr");
+        assert!(history.undo());
+        assert_eq!(history.game().player(), Position { x: 1, y: 1 });
+        assert!(!history.undo());
+    }
+
+    #[test]
+    fn redoes_a_walk_as_one_action() {
+        let mut history = history(&["#######", "#@    #", "#     #", "#######"]);
+        history.try_move(Direction::Down);
+        walk(&mut history, &[Direction::Right, Direction::Right]);
+        history.undo();
+        history.undo();
+
+        // Single moves are redone one at a time, walks all at once.
+        assert!(!history.redo_walk());
+        history.try_move(Direction::Down);
+        redo(&mut history);
+        assert_eq!(history.game().player(), Position { x: 3, y: 2 });
+        assert!(!history.redo_walk());
+        history.undo();
+        assert_eq!(history.game().lurd(), "d");
+    }
+
+    #[test]
+    fn undoes_and_redoes_a_walk_ending_in_a_push() {
+        let mut history = history(&["#######", "#@ $ .#", "#######"]);
+        walk(&mut history, &[Direction::Right, Direction::Right]);
+        assert_eq!(history.game().lurd(), "rR");
+
+        history.undo();
+        assert_eq!(history.game().lurd(), "");
+        assert!(history
+            .game()
+            .puzzle()
+            .blocks
+            .contains(&Position { x: 3, y: 1 }));
+
+        redo(&mut history);
+        assert_eq!(history.game().lurd(), "rR");
+        assert_eq!(history.game().pushes(), 1);
+        history.undo();
+        assert_eq!(history.game().lurd(), "");
+    }
+
+    #[test]
+    fn a_new_move_forgets_the_undone_walks() {
+        let mut history = history(&["#######", "#@    #", "#     #", "#######"]);
+        walk(&mut history, &[Direction::Right, Direction::Right]);
+        history.undo();
+        history.try_move(Direction::Down);
+        history.try_move(Direction::Up);
+        assert!(!history.redo_walk());
+    }
+
+    #[test]
+    fn undoing_a_restart_puts_every_move_back() {
+        let mut history = history(&["#######", "#@ $ .#", "#     #", "#######"]);
+        history.try_move(Direction::Down);
+        walk(
+            &mut history,
+            &[Direction::Up, Direction::Right, Direction::Right],
+        );
+        assert!(history.restart());
+        assert_eq!(history.game().lurd(), "");
+
+        assert!(history.undo());
+        assert_eq!(history.game().lurd(), "durR");
+        assert_eq!(history.game().pushes(), 1);
+        // The walk is still undone as one action.
+        history.undo();
+        assert_eq!(history.game().lurd(), "d");
+    }
+
+    #[test]
+    fn a_move_after_a_restart_is_undone_on_its_own() {
+        let mut history = history(&["#######", "#@ $ .#", "#     #", "#######"]);
+        history.try_move(Direction::Down);
+        history.try_move(Direction::Right);
+        history.restart();
+        history.try_move(Direction::Right);
+
+        assert!(history.undo());
+        assert_eq!(history.game().lurd(), "");
+        assert!(!history.undo());
+    }
+}
@@ -0,0 +1,432 @@
+use std::collections::HashSet;
+
+use bevy::{
+    prelude::*,
+    render::render_resource::{Extent3d, TextureDimension, TextureFormat},
+    tasks::{block_on, AsyncComputeTaskPool, Task},
+};
+use bevy_sokoban::{
+    cell::{Cell, Layout},
+    difficulty::estimate_difficulty,
+    get_enclosed_floor_positions,
+    solver::{Puzzle, SolverConfig},
+    Position,
+};
+
+use crate::{
+    action_plugin::{Action, Actions, Bindings},
+    play_plugin::{ActiveCollection, LevelState, NextLevelEvent, Playtest},
+    save_plugin::{SaveSlot, SLOT_COUNT},
+    score_plugin::{level_key, BestScores},
+    GameState,
+};
+
+pub struct LevelSelectPlugin;
+
+const LEVELS_PER_PAGE: usize = 6;
+const THUMBNAIL_CELL_SIZE: f32 = 5.0;
+
+#[derive(Resource, Default)]
+struct LevelSelect {
+    selected: usize,
+    thumbnails: Vec<Handle<Image>>,
+}
+
+#[derive(Resource, Default)]
+struct LevelDifficulties {
+    task: Option<Task<Vec<Option<f32>>>>,
+    scores: Vec<Option<f32>>,
+}
+
+#[derive(Component)]
+struct LevelList;
+
+#[derive(Component)]
+struct LevelButton(usize);
+
+// One pixel per cell, with red walls for a level that can't be played.
+fn thumbnail(layout: &Layout) -> Image {
+    let height = layout.len();
+    let width = layout.first().map_or(0, |row| row.len());
+
+    let mut walls = HashSet::default();
+    let mut player = None;
+    for (row_index, row) in layout.iter().enumerate() {
+        for (col_index, cell) in row.iter().enumerate() {
+            let position = Position {
+                x: col_index as i32,
+                y: row_index as i32,
+            };
+            if cell.contains(Cell::WALL) {
+                walls.insert(position);
+            }
+            if cell.contains(Cell::PLAYER) {
+                player = Some(position);
+            }
+        }
+    }
+    let floors: Option<HashSet<Position>> = player
+        .and_then(|player| {
+            get_enclosed_floor_positions(player, &walls, width as i32, height as i32)
+        })
+        .map(|floors| floors.into_iter().collect());
+    let wall_color = if floors.is_some() {
+        [0x90, 0x90, 0x90, 0xff]
+    } else {
+        [0xa0, 0x20, 0x20, 0xff]
+    };
+    let floors = floors.unwrap_or_default();
+
+    let mut data = Vec::with_capacity(width * height * 4);
+    for (row_index, row) in layout.iter().enumerate() {
+        for (col_index, cell) in row.iter().enumerate() {
+            let position = Position {
+                x: col_index as i32,
+                y: row_index as i32,
+            };
+            let color: [u8; 4] = if cell.contains(Cell::WALL) {
+                wall_color
+            } else if cell.contains(Cell::BLOCK) && cell.contains(Cell::GOAL) {
+                [0xe0, 0xc0, 0x40, 0xff]
+            } else if cell.contains(Cell::BLOCK) {
+                [0xa0, 0x70, 0x40, 0xff]
+            } else if cell.contains(Cell::GOAL) {
+                [0xd0, 0x40, 0x40, 0xff]
+            } else if cell.contains(Cell::PLAYER) {
+                [0x40, 0x80, 0xe0, 0xff]
+            } else if floors.contains(&position) {
+                [0x30, 0x30, 0x30, 0xff]
+            } else {
+                [0, 0, 0, 0]
+            };
+            data.extend(color);
+        }
+    }
+
+    Image::new(
+        Extent3d {
+            width: width as u32,
+            height: height as u32,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+    )
+}
+
+fn open_level_select(mut actions: ResMut<Actions>, mut game_state: ResMut<NextState<GameState>>) {
+    if actions.just_pressed(Action::LevelSelect) {
+        actions.reset(Action::LevelSelect);
+        game_state.set(GameState::LevelSelect);
+    }
+}
+
+fn show_level_select(
+    mut commands: Commands,
+    almost_everything_query: Query<Entity, Without<Window>>,
+    active_collection: Res<ActiveCollection>,
+    level_state: Res<LevelState>,
+    save_slot: Res<SaveSlot>,
+    mut images: ResMut<Assets<Image>>,
+    mut difficulties: ResMut<LevelDifficulties>,
+) {
+    for entity in almost_everything_query.iter() {
+        commands.entity(entity).despawn();
+    }
+    commands.spawn(Camera2dBundle::default());
+
+    let thumbnails = active_collection
+        .levels
+        .iter()
+        .map(|level| images.add(thumbnail(&level.layout)))
+        .collect();
+    let current_level = match (level_state.current_level, &save_slot.in_progress) {
+        (0, Some(in_progress)) if in_progress.collection_name == active_collection.name => {
+            in_progress.level
+        }
+        (current_level, _) => current_level,
+    };
+    let selected = usize::try_from(current_level - 1)
+        .unwrap_or(0)
+        .min(active_collection.levels.len().saturating_sub(1));
+    commands.insert_resource(LevelSelect {
+        selected,
+        thumbnails,
+    });
+
+    if difficulties.task.is_none() && difficulties.scores.is_empty() {
+        let layouts: Vec<Layout> = active_collection
+            .levels
+            .iter()
+            .map(|level| level.layout.clone())
+            .collect();
+        difficulties.task = Some(AsyncComputeTaskPool::get().spawn(async move {
+            layouts
+                .iter()
+                .map(|layout| {
+                    let puzzle = Puzzle::from_layout(layout)?;
+                    estimate_difficulty(&puzzle, &SolverConfig::default())
+                        .ok()
+                        .map(|difficulty| difficulty.score)
+                })
+                .collect()
+        }));
+    }
+}
+
+fn receive_difficulties(mut difficulties: ResMut<LevelDifficulties>) {
+    let Some(task) = difficulties.task.as_mut() else {
+        return;
+    };
+    if !task.is_finished() {
+        return;
+    }
+    let scores = block_on(task);
+    difficulties.scores = scores;
+    difficulties.task = None;
+}
+
+#[allow(clippy::too_many_arguments)]
+fn choose_level(
+    keyboard_input: Res<Input<KeyCode>>,
+    actions: Res<Actions>,
+    active_collection: Res<ActiveCollection>,
+    mut level_select: ResMut<LevelSelect>,
+    button_query: Query<(&Interaction, &LevelButton), Changed<Interaction>>,
+    mut cursor_moved_reader: EventReader<CursorMoved>,
+    mut next_level_writer: EventWriter<NextLevelEvent>,
+    mut game_state: ResMut<NextState<GameState>>,
+) {
+    let level_count = active_collection.levels.len();
+    if level_count == 0 {
+        return;
+    }
+
+    // Rows are rebuilt under a resting mouse, so hovering only selects when the mouse moved.
+    let cursor_moved = cursor_moved_reader.read().count() > 0;
+    let mut chosen = None;
+    for (interaction, button) in button_query.iter() {
+        match interaction {
+            Interaction::Pressed => chosen = Some(button.0),
+            Interaction::Hovered if cursor_moved && level_select.selected != button.0 => {
+                level_select.selected = button.0
+            }
+            _ => {}
+        }
+    }
+
+    if keyboard_input.just_pressed(KeyCode::Up) {
+        level_select.selected = level_select.selected.saturating_sub(1);
+    } else if keyboard_input.just_pressed(KeyCode::Down) {
+        level_select.selected = (level_select.selected + 1).min(level_count - 1);
+    } else if keyboard_input.just_pressed(KeyCode::PageUp) {
+        level_select.selected = level_select.selected.saturating_sub(LEVELS_PER_PAGE);
+    } else if keyboard_input.just_pressed(KeyCode::PageDown) {
+        level_select.selected = (level_select.selected + LEVELS_PER_PAGE).min(level_count - 1);
+    } else if actions.just_pressed(Action::Confirm) {
+        chosen = Some(level_select.selected);
+    }
+
+    if let Some(index) = chosen {
+        next_level_writer.send(NextLevelEvent(index as i32 + 1));
+        game_state.set(GameState::Playing);
+    }
+}
+
+fn level_description(
+    active_collection: &ActiveCollection,
+    best_scores: &BestScores,
+    save_slot: &SaveSlot,
+    difficulties: &LevelDifficulties,
+    index: usize,
+) -> (String, Color) {
+    let level = &active_collection.levels[index];
+    let mut description = format!(
+        "{}. {}",
+        index + 1,
+        level.title.as_deref().unwrap_or("Untitled")
+    );
+
+    let key = level_key(&active_collection.name, index as i32 + 1);
+    let best_score = best_scores.get(&key);
+    let is_reached = index == 0 || save_slot.unlocked.contains(&key);
+    let moves_in_progress = save_slot.moves_in_progress(&active_collection.name, index as i32 + 1);
+    let color = match best_score {
+        Some(best_score) => {
+            description.push_str(&format!(
+                "\nSolved, best {} moves / {} pushes",
+                best_score.fewest_moves.moves, best_score.fewest_pushes.pushes
+            ));
+            Color::rgb(0.6, 1.0, 0.6)
+        }
+        None if is_reached => {
+            description.push_str("\nNot solved yet");
+            Color::WHITE
+        }
+        None => {
+            description.push_str("\nNot reached yet");
+            Color::GRAY
+        }
+    };
+    if let Some(moves) = moves_in_progress.filter(|moves| !moves.is_empty()) {
+        description.push_str(&format!(", {} moves in", moves.len()));
+    }
+    match difficulties.scores.get(index) {
+        Some(Some(score)) => description.push_str(&format!("\nDifficulty {:.1}", score)),
+        Some(None) => description.push_str("\nDifficulty unknown"),
+        None => description.push_str("\nRating..."),
+    }
+    (description, color)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn draw_level_list(
+    mut commands: Commands,
+    active_collection: Res<ActiveCollection>,
+    level_select: Res<LevelSelect>,
+    best_scores: Res<BestScores>,
+    save_slot: Res<SaveSlot>,
+    difficulties: Res<LevelDifficulties>,
+    bindings: Res<Bindings>,
+    list_query: Query<Entity, With<LevelList>>,
+) {
+    if !level_select.is_changed()
+        && !difficulties.is_changed()
+        && !save_slot.is_changed()
+        && !list_query.is_empty()
+    {
+        return;
+    }
+    for entity in list_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    let title = active_collection.title.as_deref().unwrap_or("Levels");
+    let first = level_select.selected / LEVELS_PER_PAGE * LEVELS_PER_PAGE;
+    let last = (first + LEVELS_PER_PAGE).min(active_collection.levels.len());
+
+    commands
+        .spawn((
+            LevelList,
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    padding: UiRect::all(Val::Px(10.0)),
+                    row_gap: Val::Px(4.0),
+                    ..default()
+                },
+                ..default()
+            },
+        ))
+        .with_children(|list| {
+            list.spawn(TextBundle::from_section(
+                format!(
+                    "{}, save slot {} of {}\nUp/Down and {} or click to play, 1-{} to switch \
+                     slots, {} for controls, {} in a level to come back",
+                    title,
+                    save_slot.number,
+                    SLOT_COUNT,
+                    bindings.key_label(Action::Confirm),
+                    SLOT_COUNT,
+                    bindings.key_label(Action::Settings),
+                    bindings.key_label(Action::LevelSelect)
+                ),
+                TextStyle {
+                    font_size: 16.0,
+                    color: Color::WHITE,
+                    ..default()
+                },
+            ));
+
+            for index in first..last {
+                let (description, color) = level_description(
+                    &active_collection,
+                    &best_scores,
+                    &save_slot,
+                    &difficulties,
+                    index,
+                );
+                let layout = &active_collection.levels[index].layout;
+                let background = if index == level_select.selected {
+                    Color::rgb(0.25, 0.25, 0.35)
+                } else {
+                    Color::rgb(0.12, 0.12, 0.12)
+                };
+
+                list.spawn((
+                    LevelButton(index),
+                    ButtonBundle {
+                        style: Style {
+                            align_items: AlignItems::Center,
+                            column_gap: Val::Px(10.0),
+                            padding: UiRect::all(Val::Px(4.0)),
+                            ..default()
+                        },
+                        background_color: background.into(),
+                        ..default()
+                    },
+                ))
+                .with_children(|button| {
+                    button.spawn(ImageBundle {
+                        style: Style {
+                            width: Val::Px(
+                                layout.first().map_or(0, |row| row.len()) as f32
+                                    * THUMBNAIL_CELL_SIZE,
+                            ),
+                            height: Val::Px(layout.len() as f32 * THUMBNAIL_CELL_SIZE),
+                            ..default()
+                        },
+                        image: UiImage::new(level_select.thumbnails[index].clone()),
+                        ..default()
+                    });
+                    button.spawn(TextBundle::from_section(
+                        description,
+                        TextStyle {
+                            font_size: 14.0,
+                            color,
+                            ..default()
+                        },
+                    ));
+                });
+            }
+
+            list.spawn(TextBundle::from_section(
+                format!(
+                    "Levels {}-{} of {}",
+                    first + 1,
+                    last,
+                    active_collection.levels.len()
+                ),
+                TextStyle {
+                    font_size: 14.0,
+                    color: Color::GRAY,
+                    ..default()
+                },
+            ));
+        });
+}
+
+impl Plugin for LevelSelectPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(LevelDifficulties::default())
+            .add_systems(
+                Update,
+                open_level_select.run_if(
+                    in_state(GameState::Playing).and_then(not(resource_exists::<Playtest>())),
+                ),
+            )
+            .add_systems(OnEnter(GameState::LevelSelect), show_level_select)
+            .add_systems(
+                Update,
+                (
+                    receive_difficulties,
+                    choose_level,
+                    draw_level_list.after(choose_level),
+                )
+                    .run_if(in_state(GameState::LevelSelect)),
+            );
+    }
+}
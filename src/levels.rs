@@ -0,0 +1,485 @@
+use std::{
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    cell::{Cell, Layout},
+    get_enclosed_floor_positions,
+    solver::Puzzle,
+    storage::data_directory,
+};
+
+#[derive(Debug)]
+pub enum LevelError {
+    Io(io::Error),
+    Empty,
+    UnknownGlyph {
+        line: usize,
+        column: usize,
+        glyph: char,
+    },
+    RaggedRow {
+        line: usize,
+        expected: usize,
+        found: usize,
+    },
+    NoPlayer {
+        line: usize,
+    },
+    MultiplePlayers {
+        line: usize,
+        count: usize,
+    },
+    NotEnclosed {
+        line: usize,
+    },
+}
+
+impl fmt::Display for LevelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LevelError::Io(error) => write!(f, "{}", error),
+            LevelError::Empty => write!(f, "level has no rows"),
+            LevelError::UnknownGlyph {
+                line,
+                column,
+                glyph,
+            } => write!(
+                f,
+                "unknown glyph {:?} at line {}, column {}",
+                glyph, line, column
+            ),
+            LevelError::RaggedRow {
+                line,
+                expected,
+                found,
+            } => write!(
+                f,
+                "row at line {} is {} cells wide, expected {}",
+                line, found, expected
+            ),
+            LevelError::NoPlayer { line } => write!(f, "level at line {} has no player", line),
+            LevelError::MultiplePlayers { line, count } => write!(
+                f,
+                "level at line {} has {} players, expected one",
+                line, count
+            ),
+            LevelError::NotEnclosed { line } => write!(
+                f,
+                "level at line {} has a gap in its walls the player can walk out of",
+                line
+            ),
+        }
+    }
+}
+
+impl std::error::Error for LevelError {}
+
+impl From<io::Error> for LevelError {
+    fn from(error: io::Error) -> Self {
+        LevelError::Io(error)
+    }
+}
+
+#[derive(Clone, Default, Debug)]
+pub struct LevelEntry {
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub comments: Vec<String>,
+    pub layout: Layout,
+}
+
+#[derive(Clone, Default, Debug)]
+pub struct Collection {
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub comments: Vec<String>,
+    pub levels: Vec<LevelEntry>,
+}
+
+#[derive(Default, Debug)]
+pub struct LevelByLevel {
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub comments: Vec<String>,
+    pub levels: Vec<Result<LevelEntry, LevelError>>,
+}
+
+pub fn user_levels_directory() -> PathBuf {
+    data_directory().join("levels")
+}
+
+pub fn user_level_path(name: &str) -> PathBuf {
+    user_levels_directory().join(format!("{}.xsb", name))
+}
+
+pub fn list_user_levels() -> Vec<String> {
+    let Ok(entries) = fs::read_dir(user_levels_directory()) else {
+        return Vec::new();
+    };
+
+    let mut names: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "xsb"))
+        .filter_map(|path| Some(path.file_stem()?.to_string_lossy().into_owned()))
+        .collect();
+    names.sort();
+    names
+}
+
+pub fn save_user_level(name: &str, layout: &Layout) -> Result<PathBuf, LevelError> {
+    fs::create_dir_all(user_levels_directory())?;
+    let path = user_level_path(name);
+    fs::write(&path, format!("{}Title: {}\n", to_xsb(layout), name))?;
+    Ok(path)
+}
+
+pub fn load_collection(path: &Path) -> Result<Collection, LevelError> {
+    parse_collection(&fs::read_to_string(path)?)
+}
+
+// Text before the first level describes the collection itself.
+pub fn parse_collection(text: &str) -> Result<Collection, LevelError> {
+    let pack = parse_level_by_level(text);
+    if pack.levels.is_empty() {
+        return Err(LevelError::Empty);
+    }
+    Ok(Collection {
+        title: pack.title,
+        author: pack.author,
+        comments: pack.comments,
+        levels: pack.levels.into_iter().collect::<Result<_, _>>()?,
+    })
+}
+
+// Keeps going past levels that fail to parse, dropping their metadata.
+pub fn parse_level_by_level(text: &str) -> LevelByLevel {
+    let mut pack = LevelByLevel::default();
+    let lines: Vec<(usize, &str)> = numbered_lines(text).collect();
+
+    for paragraph in lines.split(|(_, line)| line.trim().is_empty()) {
+        if paragraph.is_empty() {
+            continue;
+        }
+
+        let board_start = paragraph.iter().position(|(_, line)| is_board_line(line));
+        let Some(board_start) = board_start else {
+            match pack.levels.last_mut() {
+                Some(Ok(level)) => read_metadata(
+                    paragraph,
+                    &mut level.title,
+                    &mut level.author,
+                    &mut level.comments,
+                ),
+                Some(Err(_)) => {}
+                None => read_metadata(
+                    paragraph,
+                    &mut pack.title,
+                    &mut pack.author,
+                    &mut pack.comments,
+                ),
+            }
+            continue;
+        };
+        let board_end = paragraph[board_start..]
+            .iter()
+            .position(|(_, line)| !is_board_line(line))
+            .map_or(paragraph.len(), |length| board_start + length);
+
+        let layout = match parse_board(&paragraph[board_start..board_end]) {
+            Ok(layout) => layout,
+            Err(error) => {
+                pack.levels.push(Err(error));
+                continue;
+            }
+        };
+        let mut level = LevelEntry {
+            layout,
+            ..Default::default()
+        };
+        read_metadata(
+            &paragraph[..board_start],
+            &mut level.title,
+            &mut level.author,
+            &mut level.comments,
+        );
+        read_metadata(
+            &paragraph[board_end..],
+            &mut level.title,
+            &mut level.author,
+            &mut level.comments,
+        );
+        pack.levels.push(Ok(level));
+    }
+    pack
+}
+
+pub fn to_xsb(layout: &Layout) -> String {
+    layout
+        .iter()
+        .map(|row| row.iter().map(|cell| cell.glyph()).collect::<String>() + "\n")
+        .collect()
+}
+
+// Comments get a `;` so one that looks like a row of walls isn't read as a board.
+pub fn collection_to_xsb(collection: &Collection) -> String {
+    let mut text = String::new();
+    write_metadata(
+        &mut text,
+        &collection.title,
+        &collection.author,
+        &collection.comments,
+    );
+    for level in &collection.levels {
+        if !text.is_empty() {
+            text.push('\n');
+        }
+        text.push_str(&to_xsb(&level.layout));
+        write_metadata(&mut text, &level.title, &level.author, &level.comments);
+    }
+    text
+}
+
+fn write_metadata(
+    text: &mut String,
+    title: &Option<String>,
+    author: &Option<String>,
+    comments: &[String],
+) {
+    if let Some(title) = title {
+        text.push_str(&format!("Title: {}\n", title));
+    }
+    if let Some(author) = author {
+        text.push_str(&format!("Author: {}\n", author));
+    }
+    for comment in comments {
+        text.push_str(&format!("; {}\n", comment));
+    }
+}
+
+fn numbered_lines(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    text.lines()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line.trim_end_matches('\r')))
+}
+
+fn is_board_line(line: &str) -> bool {
+    let trimmed = line.trim_start();
+    trimmed.starts_with('#')
+        || (trimmed.contains('#') && trimmed.chars().all(|glyph| "#@+$*. -_".contains(glyph)))
+}
+
+fn read_metadata(
+    lines: &[(usize, &str)],
+    title: &mut Option<String>,
+    author: &mut Option<String>,
+    comments: &mut Vec<String>,
+) {
+    for (_, line) in lines {
+        let line = line.trim();
+        if let Some(value) = line.strip_prefix("Title:") {
+            *title = Some(value.trim().to_string());
+        } else if let Some(value) = line.strip_prefix("Author:") {
+            *author = Some(value.trim().to_string());
+        } else if let Some(comment) = line.strip_prefix(';') {
+            comments.push(comment.trim().to_string());
+        } else if let Some(comment) = line.strip_prefix("Comment:") {
+            comments.push(comment.trim().to_string());
+        } else {
+            comments.push(line.to_string());
+        }
+    }
+}
+
+fn parse_board(lines: &[(usize, &str)]) -> Result<Layout, LevelError> {
+    let mut level: Layout = Vec::new();
+    let mut players = 0;
+    for (line_number, line) in lines {
+        let mut row = Vec::new();
+        for (column_index, glyph) in line.chars().enumerate() {
+            let Some(cell) = Cell::from_glyph(glyph) else {
+                return Err(LevelError::UnknownGlyph {
+                    line: *line_number,
+                    column: column_index + 1,
+                    glyph,
+                });
+            };
+            if cell.contains(Cell::PLAYER) {
+                players += 1;
+            }
+            row.push(cell);
+        }
+
+        if let Some(first_row) = level.first() {
+            let expected = first_row.len();
+            if row.len() != expected {
+                return Err(LevelError::RaggedRow {
+                    line: *line_number,
+                    expected,
+                    found: row.len(),
+                });
+            }
+        }
+        level.push(row);
+    }
+
+    let line = lines.first().map_or(0, |(line_number, _)| *line_number);
+    match players {
+        0 => Err(LevelError::NoPlayer { line }),
+        1 if !is_enclosed(&level) => Err(LevelError::NotEnclosed { line }),
+        1 => Ok(level),
+        count => Err(LevelError::MultiplePlayers { line, count }),
+    }
+}
+
+pub fn is_enclosed(layout: &Layout) -> bool {
+    let Some(puzzle) = Puzzle::from_layout(layout) else {
+        return false;
+    };
+    let width = layout.first().map_or(0, |row| row.len()) as i32;
+    let height = layout.len() as i32;
+    get_enclosed_floor_positions(puzzle.player, &puzzle.walls, width, height).is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PACK: &str = "\
+Title: Test Pack
+Author: Someone
+A pack for the tests.
+
+; 1
+#####
+#@$.#
+#####
+Title: First
+
+Title: Second
+Author: Someone Else
+######
+#+*$ #
+#  . #
+######
+Comment: Two goals
+";
+
+    #[test]
+    fn parses_levels_and_their_metadata() {
+        let collection = parse_collection(PACK).unwrap();
+        assert_eq!(collection.title.as_deref(), Some("Test Pack"));
+        assert_eq!(collection.author.as_deref(), Some("Someone"));
+        assert_eq!(collection.comments, ["A pack for the tests."]);
+        assert_eq!(collection.levels.len(), 2);
+
+        let first = &collection.levels[0];
+        assert_eq!(first.title.as_deref(), Some("First"));
+        assert_eq!(first.author, None);
+        assert_eq!(first.comments, ["1"]);
+        assert_eq!(to_xsb(&first.layout), "#####\n#@$.#\n#####\n");
+
+        let second = &collection.levels[1];
+        assert_eq!(second.title.as_deref(), Some("Second"));
+        assert_eq!(second.author.as_deref(), Some("Someone Else"));
+        assert_eq!(second.comments, ["Two goals"]);
+        assert_eq!(second.layout[1][1], Cell::PLAYER | Cell::GOAL);
+        assert_eq!(second.layout[1][2], Cell::BLOCK | Cell::GOAL);
+        assert_eq!(second.layout[1][3], Cell::BLOCK);
+    }
+
+    #[test]
+    fn reports_io_errors() {
+        let error = load_collection(Path::new("no/such/collection.sok")).unwrap_err();
+        assert!(matches!(error, LevelError::Io(_)));
+    }
+
+    #[test]
+    fn reports_a_file_without_levels() {
+        let error = parse_collection("Title: Nothing here\n\n; just comments\n").unwrap_err();
+        assert!(matches!(error, LevelError::Empty));
+    }
+
+    #[test]
+    fn reports_unknown_glyphs() {
+        let error = parse_collection("Title: Bad\n\n#####\n#@x.#\n#####\n").unwrap_err();
+        assert!(matches!(
+            error,
+            LevelError::UnknownGlyph {
+                line: 4,
+                column: 3,
+                glyph: 'x'
+            }
+        ));
+    }
+
+    #[test]
+    fn reports_ragged_rows() {
+        let error = parse_collection("#####\n#@$.#\n####\n").unwrap_err();
+        assert!(matches!(
+            error,
+            LevelError::RaggedRow {
+                line: 3,
+                expected: 5,
+                found: 4
+            }
+        ));
+    }
+
+    #[test]
+    fn reports_a_level_without_a_player() {
+        let text = "#####\n#@$.#\n#####\n\n#####\n# $.#\n#####\n";
+        let error = parse_collection(text).unwrap_err();
+        assert!(matches!(error, LevelError::NoPlayer { line: 5 }));
+    }
+
+    #[test]
+    fn reports_a_level_with_several_players() {
+        let error = parse_collection("\n######\n#@$.@#\n######\n").unwrap_err();
+        assert!(matches!(
+            error,
+            LevelError::MultiplePlayers { line: 2, count: 2 }
+        ));
+    }
+
+    #[test]
+    fn reports_a_level_with_a_gap_in_its_walls() {
+        let error = parse_collection("Title: Open\n#####\n#@$. \n#####\n").unwrap_err();
+        assert!(matches!(error, LevelError::NotEnclosed { line: 2 }));
+    }
+
+    #[test]
+    fn keeps_parsing_past_a_broken_level() {
+        let text = "#####\n#@x.#\n#####\nTitle: Broken\n\n#####\n#@$.#\n#####\nTitle: Fine\n";
+        let pack = parse_level_by_level(text);
+        assert_eq!(pack.levels.len(), 2);
+        assert!(matches!(
+            pack.levels[0],
+            Err(LevelError::UnknownGlyph { line: 2, .. })
+        ));
+        let fine = pack.levels[1].as_ref().unwrap();
+        assert_eq!(fine.title.as_deref(), Some("Fine"));
+    }
+
+    #[test]
+    fn to_xsb_round_trips() {
+        let collection = parse_collection(PACK).unwrap();
+        let reparsed = parse_collection(&collection_to_xsb(&collection)).unwrap();
+        assert_eq!(reparsed.title, collection.title);
+        assert_eq!(reparsed.author, collection.author);
+        assert_eq!(reparsed.comments, collection.comments);
+        assert_eq!(reparsed.levels.len(), collection.levels.len());
+        for (level, original) in reparsed.levels.iter().zip(&collection.levels) {
+            assert_eq!(level.layout, original.layout);
+            assert_eq!(level.title, original.title);
+            assert_eq!(level.author, original.author);
+            assert_eq!(level.comments, original.comments);
+        }
+
+        let layout = &collection.levels[1].layout;
+        let single = parse_collection(&to_xsb(layout)).unwrap();
+        assert_eq!(&single.levels[0].layout, layout);
+    }
+}
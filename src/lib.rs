@@ -0,0 +1,140 @@
+pub mod cell;
+pub mod check;
+pub mod deadlock;
+pub mod difficulty;
+pub mod game;
+pub mod generator;
+pub mod history;
+pub mod levels;
+pub mod path;
+pub mod solver;
+pub mod storage;
+
+#[cfg(test)]
+mod fixtures;
+
+use std::collections::HashSet;
+
+#[derive(Copy, Clone, Eq, Hash, PartialEq, Debug)]
+pub struct Position {
+    pub x: i32,
+    pub y: i32,
+}
+
+impl Position {
+    pub fn add(&self, x: i32, y: i32) -> Position {
+        Position {
+            x: self.x + x,
+            y: self.y + y,
+        }
+    }
+
+    pub fn step(&self, direction: Direction) -> Position {
+        let (x, y) = direction.offset();
+        self.add(x, y)
+    }
+}
+
+#[derive(Clone, Copy, Eq, Hash, PartialEq, Debug)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl Direction {
+    pub const ALL: [Direction; 4] = [
+        Direction::Up,
+        Direction::Down,
+        Direction::Left,
+        Direction::Right,
+    ];
+
+    pub fn offset(self) -> (i32, i32) {
+        match self {
+            Direction::Up => (0, -1),
+            Direction::Down => (0, 1),
+            Direction::Left => (-1, 0),
+            Direction::Right => (1, 0),
+        }
+    }
+
+    // LURD notation: lowercase moves, uppercase pushes.
+    pub fn to_lurd(self, is_push: bool) -> char {
+        let lurd = match self {
+            Direction::Up => 'u',
+            Direction::Down => 'd',
+            Direction::Left => 'l',
+            Direction::Right => 'r',
+        };
+        if is_push {
+            lurd.to_ascii_uppercase()
+        } else {
+            lurd
+        }
+    }
+
+    pub fn from_lurd(lurd: char) -> Option<(Direction, bool)> {
+        let direction = match lurd.to_ascii_lowercase() {
+            'u' => Direction::Up,
+            'd' => Direction::Down,
+            'l' => Direction::Left,
+            'r' => Direction::Right,
+            _ => return None,
+        };
+        Some((direction, lurd.is_ascii_uppercase()))
+    }
+
+    pub fn opposite(self) -> Direction {
+        match self {
+            Direction::Up => Direction::Down,
+            Direction::Down => Direction::Up,
+            Direction::Left => Direction::Right,
+            Direction::Right => Direction::Left,
+        }
+    }
+}
+
+// The floor the player can reach, or None when a gap in the walls lets them leave the width by
+// height level. Every flood fill is bounded like this, so an open level can't make one run forever.
+pub fn get_enclosed_floor_positions(
+    player_position: Position,
+    walls: &HashSet<Position>,
+    width: i32,
+    height: i32,
+) -> Option<Vec<Position>> {
+    let is_inside =
+        |position: Position| (0..width).contains(&position.x) && (0..height).contains(&position.y);
+    let mut visited = HashSet::new();
+    let mut to_visit = vec![player_position];
+
+    while let Some(current_position) = to_visit.pop() {
+        if visited.contains(&current_position) {
+            continue;
+        }
+        if !is_inside(current_position) {
+            return None;
+        }
+        visited.insert(current_position);
+
+        let up_position = current_position.add(0, 1);
+        if !walls.contains(&up_position) {
+            to_visit.push(up_position);
+        }
+        let down_position = current_position.add(0, -1);
+        if !walls.contains(&down_position) {
+            to_visit.push(down_position);
+        }
+        let right_position = current_position.add(1, 0);
+        if !walls.contains(&right_position) {
+            to_visit.push(right_position);
+        }
+        let left_position = current_position.add(-1, 0);
+        if !walls.contains(&left_position) {
+            to_visit.push(left_position);
+        }
+    }
+
+    Some(visited.into_iter().collect())
+}
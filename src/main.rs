@@ -1,151 +1,65 @@
+mod action_plugin;
+mod click_plugin;
+mod deadlock_plugin;
 mod edit_plugin;
+mod gamepad_plugin;
+mod hint_plugin;
+mod level_select_plugin;
 mod play_plugin;
+mod replay_plugin;
+mod save_plugin;
+mod score_plugin;
+mod settings_plugin;
+mod solution_plugin;
 mod tiles;
 
-use bevy::{
-    prelude::*,
-    sprite::Anchor,
-    utils::{HashMap, HashSet},
-    window::WindowResolution,
+use std::collections::HashSet;
+
+use action_plugin::{Action, ActionPlugin, Actions};
+use bevy::{prelude::*, sprite::Anchor, utils::HashMap, window::WindowResolution};
+use bevy_sokoban::{
+    cell::{Cell, Layout},
+    game::Game,
+    get_enclosed_floor_positions,
+    solver::{dead_squares, Puzzle},
+    Direction, Position,
 };
+use click_plugin::ClickPlugin;
+use deadlock_plugin::{DeadSquares, DeadlockPlugin};
 use edit_plugin::EditPlugin;
-use play_plugin::{LevelState, NextLevelEvent, PlayPlugin, Player, UndoStack};
-use tiles::spawn_floor;
+use gamepad_plugin::GamepadPlugin;
+use hint_plugin::HintPlugin;
+use level_select_plugin::LevelSelectPlugin;
+use play_plugin::{LevelState, PlayPlugin, Player};
+use replay_plugin::ReplayPlugin;
+use save_plugin::SavePlugin;
+use score_plugin::ScorePlugin;
+use settings_plugin::SettingsPlugin;
+use solution_plugin::SolutionPlugin;
+use tiles::{spawn_floor, Translation, TILE_SIZE};
 
 #[derive(States, Default, Debug, PartialEq, Eq, Hash, Copy, Clone)]
 pub enum GameState {
     #[default]
     Startup,
+    LevelSelect,
     Playing,
     Editing,
     Paused,
+    Replaying,
+    CollectionComplete,
+    Settings,
 }
 
-pub const TILE_SIZE: f32 = 16.0;
-
-#[derive(Component, Copy, Clone, Eq, Hash, PartialEq, Debug)]
-pub struct Position {
-    x: i32,
-    y: i32,
-}
-
-impl Position {
-    fn add(&self, x: i32, y: i32) -> Position {
-        Position {
-            x: self.x + x,
-            y: self.y + y,
-        }
-    }
-
-    fn from_translation(translation: Vec3) -> Position {
-        Position {
-            x: (translation.x / TILE_SIZE) as i32,
-            y: (-translation.y / TILE_SIZE) as i32,
-        }
-    }
-
-    fn to_translation(self) -> Vec3 {
-        self.to_translation_z(1.0)
-    }
-
-    fn to_translation_z(self, z: f32) -> Vec3 {
-        Vec3::new(self.x as f32 * TILE_SIZE, self.y as f32 * -TILE_SIZE, z)
-    }
-}
-
-#[derive(Clone, PartialEq)]
-pub enum Obstacle {
-    Block,
-    Wall,
-}
-
-pub fn level_one() -> Vec<Vec<i32>> {
-    vec![
-        vec![8, 8, 8, 8, 8, 8],
-        vec![8, 4, 0, 2, 1, 8],
-        vec![8, 8, 8, 0, 0, 8],
-        vec![0, 0, 8, 8, 8, 8],
-    ]
-}
-
-pub fn level_two() -> Vec<Vec<i32>> {
-    vec![
-        vec![8, 8, 8, 0, 8, 8, 8, 8],
-        vec![8, 4, 8, 8, 8, 2, 1, 8],
-        vec![8, 2, 0, 0, 0, 0, 2, 8],
-        vec![8, 0, 0, 0, 2, 0, 0, 8],
-        vec![8, 8, 8, 8, 8, 8, 8, 8],
-    ]
-}
-
-pub fn level_three() -> Vec<Vec<i32>> {
-    vec![
-        vec![0, 8, 8, 8, 8, 8, 8, 8, 8, 8, 0],
-        vec![8, 8, 0, 0, 0, 0, 0, 0, 0, 8, 8],
-        vec![8, 4, 2, 2, 0, 0, 2, 0, 2, 1, 8],
-        vec![8, 2, 2, 0, 0, 0, 2, 2, 2, 2, 8],
-        vec![8, 0, 0, 0, 0, 0, 0, 0, 2, 2, 8],
-        vec![8, 2, 0, 0, 0, 0, 0, 0, 0, 0, 8],
-        vec![8, 8, 0, 0, 0, 0, 0, 0, 0, 8, 8],
-        vec![0, 8, 8, 8, 8, 8, 8, 8, 8, 8, 0],
-    ]
-}
-
-pub fn level_four() -> Vec<Vec<i32>> {
-    vec![
-        vec![8, 8, 8, 0, 0],
-        vec![8, 1, 8, 8, 0],
-        vec![8, 4, 0, 8, 8],
-        vec![8, 2, 0, 0, 8],
-        vec![8, 0, 0, 0, 8],
-        vec![8, 8, 8, 8, 8],
-    ]
-}
-
-fn get_floor_positions(
-    player_position: Position,
-    obstacles: HashMap<Position, (Entity, Obstacle)>,
-) -> Vec<Position> {
-    fn is_not_wall(obstacle: Option<(Entity, Obstacle)>) -> bool {
-        obstacle.is_none() || obstacle.unwrap().1 == Obstacle::Block
-    }
-
-    let mut visited = HashSet::default();
-    let mut to_visit = vec![player_position];
-
-    while !to_visit.is_empty() {
-        let current_position = to_visit.pop().unwrap();
-        if visited.contains(&current_position) {
-            continue;
-        }
-        visited.insert(current_position);
-
-        let up_position = current_position.add(0, 1);
-        if is_not_wall(obstacles.get(&up_position).cloned()) {
-            to_visit.push(up_position);
-        }
-        let down_position = current_position.add(0, -1);
-        if is_not_wall(obstacles.get(&down_position).cloned()) {
-            to_visit.push(down_position);
-        }
-        let right_position = current_position.add(1, 0);
-        if is_not_wall(obstacles.get(&right_position).cloned()) {
-            to_visit.push(right_position);
-        }
-        let left_position = current_position.add(-1, 0);
-        if is_not_wall(obstacles.get(&left_position).cloned()) {
-            to_visit.push(left_position);
-        }
-    }
-
-    visited.into_iter().collect()
-}
+pub const MOVE_SECONDS: f32 = 0.3;
 
+// Plays the saved LURD moves on the level, stopping at the first one that no longer fits.
 fn level_setup(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
     level: i32,
-    level_layout: Vec<Vec<i32>>,
+    level_layout: Layout,
+    moves: &str,
 ) {
     let last_row_index = level_layout.len() as i32;
     let last_col_index = level_layout.first().unwrap().len() as i32;
@@ -164,9 +78,11 @@ fn level_setup(
         ..default()
     });
 
-    let mut obstacles = HashMap::default();
-    let mut goals = HashMap::default();
+    let mut walls = HashSet::default();
+    let mut goals = HashSet::default();
+    let mut blocks = HashMap::default();
     let mut player_position = None;
+    let mut player_entity = None;
 
     let wall_texture: Handle<Image> = asset_server.load("wall.png");
     let goal_texture: Handle<Image> = asset_server.load("goal.png");
@@ -175,16 +91,18 @@ fn level_setup(
 
     for (row_index, row) in level_layout.iter().enumerate() {
         for (col_index, col) in row.iter().enumerate() {
-            match col {
-                1 => {
-                    player_position = Some(Position {
-                        x: col_index as i32,
-                        y: row_index as i32,
-                    });
-                    commands.spawn((
+            let position = Position {
+                x: col_index as i32,
+                y: row_index as i32,
+            };
+
+            if col.contains(Cell::PLAYER) {
+                player_position = Some(position);
+                let player_id = commands
+                    .spawn((
                         Player {
                             is_moving: false,
-                            move_timer: Timer::from_seconds(0.3, TimerMode::Once),
+                            move_timer: Timer::from_seconds(MOVE_SECONDS, TimerMode::Once),
                         },
                         SpriteBundle {
                             sprite: Sprite {
@@ -192,108 +110,105 @@ fn level_setup(
                                 ..default()
                             },
                             texture: player_texture.clone(),
-                            transform: Transform::from_translation(
-                                player_position.unwrap().to_translation(),
-                            ),
+                            transform: Transform::from_translation(position.to_translation()),
                             ..default()
                         },
-                    ));
-                }
-                2 => {
-                    let position = Position {
-                        x: col_index as i32,
-                        y: row_index as i32,
-                    };
+                    ))
+                    .id();
+                player_entity = Some(player_id);
+            }
 
-                    let block_id = commands
-                        .spawn(SpriteBundle {
-                            sprite: Sprite {
-                                anchor: Anchor::TopLeft,
-                                ..default()
-                            },
-                            texture: block_texture.clone(),
-                            transform: Transform::from_translation(position.to_translation()),
+            if col.contains(Cell::BLOCK) {
+                let block_id = commands
+                    .spawn(SpriteBundle {
+                        sprite: Sprite {
+                            anchor: Anchor::TopLeft,
                             ..default()
-                        })
-                        .id();
-                    obstacles.insert(
-                        Position {
-                            x: col_index as i32,
-                            y: row_index as i32,
                         },
-                        (block_id, Obstacle::Block),
-                    );
-                }
-                4 => {
-                    let position = Position {
-                        x: col_index as i32,
-                        y: row_index as i32,
-                    };
+                        texture: block_texture.clone(),
+                        transform: Transform::from_translation(position.to_translation()),
+                        ..default()
+                    })
+                    .id();
+                blocks.insert(position, block_id);
+            }
 
-                    let goal_id = commands
-                        .spawn(SpriteBundle {
-                            sprite: Sprite {
-                                anchor: Anchor::TopLeft,
-                                ..default()
-                            },
-                            texture: goal_texture.clone(),
-                            transform: Transform::from_translation(position.to_translation_z(0.5)),
-                            ..default()
-                        })
-                        .id();
-                    goals.insert(position, goal_id);
-                }
-                8 => {
-                    let position = Position {
-                        x: col_index as i32,
-                        y: row_index as i32,
-                    };
+            if col.contains(Cell::GOAL) {
+                commands.spawn(SpriteBundle {
+                    sprite: Sprite {
+                        anchor: Anchor::TopLeft,
+                        ..default()
+                    },
+                    texture: goal_texture.clone(),
+                    transform: Transform::from_translation(position.to_translation_z(0.5)),
+                    ..default()
+                });
+                goals.insert(position);
+            }
 
-                    let wall_id = commands
-                        .spawn(SpriteBundle {
-                            sprite: Sprite {
-                                anchor: Anchor::TopLeft,
-                                ..default()
-                            },
-                            texture: wall_texture.clone(),
-                            transform: Transform::from_translation(position.to_translation()),
-                            ..default()
-                        })
-                        .id();
-                    obstacles.insert(position, (wall_id, Obstacle::Wall));
-                }
-                0 | _ => {}
+            if col.contains(Cell::WALL) {
+                commands.spawn(SpriteBundle {
+                    sprite: Sprite {
+                        anchor: Anchor::TopLeft,
+                        ..default()
+                    },
+                    texture: wall_texture.clone(),
+                    transform: Transform::from_translation(position.to_translation()),
+                    ..default()
+                });
+                walls.insert(position);
             }
         }
     }
 
-    for floor_position in get_floor_positions(player_position.unwrap(), obstacles.clone()) {
+    let floor_positions = get_enclosed_floor_positions(
+        player_position.unwrap(),
+        &walls,
+        last_col_index,
+        last_row_index,
+    );
+    for floor_position in floor_positions.unwrap_or_default() {
         commands.spawn(spawn_floor(&asset_server, floor_position));
     }
 
-    commands.insert_resource(LevelState {
-        current_level: level,
-        obstacles: obstacles,
-        goals: goals,
-        player_position: player_position.unwrap(),
-    });
-    commands.insert_resource(UndoStack(Vec::default()));
+    let puzzle = Puzzle {
+        walls,
+        goals,
+        blocks: blocks.keys().copied().collect(),
+        player: player_position.unwrap(),
+    };
+    commands.insert_resource(DeadSquares(dead_squares(&puzzle)));
+    let mut level_state = LevelState::new(level, Game::new(puzzle), blocks);
+
+    if !moves.is_empty() {
+        for (direction, _) in moves.chars().filter_map(Direction::from_lurd) {
+            if !level_state.try_move(direction).is_legal() {
+                break;
+            }
+        }
+        for (position, entity) in level_state.blocks.iter() {
+            commands
+                .entity(*entity)
+                .insert(Transform::from_translation(position.to_translation()));
+        }
+        if let Some(player_entity) = player_entity {
+            commands
+                .entity(player_entity)
+                .insert(Transform::from_translation(
+                    level_state.game().player().to_translation(),
+                ));
+        }
+    }
+    commands.insert_resource(level_state);
 }
 
-fn start_playing(
-    mut next_level_writer: EventWriter<NextLevelEvent>,
-    mut game_state: ResMut<NextState<GameState>>,
-) {
-    next_level_writer.send(NextLevelEvent(1));
-    game_state.set(GameState::Playing);
+fn start_game(mut game_state: ResMut<NextState<GameState>>) {
+    game_state.set(GameState::LevelSelect);
 }
 
-fn unpause_game(
-    mut keyboard_input: ResMut<Input<KeyCode>>,
-    mut game_state: ResMut<NextState<GameState>>,
-) {
-    if keyboard_input.just_pressed(KeyCode::Space) {
-        keyboard_input.reset(KeyCode::Space);
+fn unpause_game(mut actions: ResMut<Actions>, mut game_state: ResMut<NextState<GameState>>) {
+    if actions.just_pressed(Action::Pause) {
+        actions.reset(Action::Pause);
         game_state.set(GameState::Playing);
     }
 }
@@ -314,9 +229,20 @@ fn main() {
         )
         .add_systems(Update, bevy::window::close_on_esc)
         .add_state::<GameState>()
-        .add_systems(Update, start_playing.run_if(in_state(GameState::Startup)))
+        .add_systems(Update, start_game.run_if(in_state(GameState::Startup)))
         .add_systems(Update, unpause_game.run_if(in_state(GameState::Paused)))
         .add_plugins(PlayPlugin)
         .add_plugins(EditPlugin)
+        .add_plugins(HintPlugin)
+        .add_plugins(DeadlockPlugin)
+        .add_plugins(ScorePlugin)
+        .add_plugins(SolutionPlugin)
+        .add_plugins(ReplayPlugin)
+        .add_plugins(LevelSelectPlugin)
+        .add_plugins(SavePlugin)
+        .add_plugins(ClickPlugin)
+        .add_plugins(GamepadPlugin)
+        .add_plugins(ActionPlugin)
+        .add_plugins(SettingsPlugin)
         .run();
 }
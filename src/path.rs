@@ -0,0 +1,136 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    hash::Hash,
+};
+
+use crate::{solver::Puzzle, Direction, Position};
+
+pub fn find_walk(puzzle: &Puzzle, to: Position) -> Option<Vec<Direction>> {
+    let floor: HashSet<Position> = puzzle.floor_positions()?.into_iter().collect();
+    if !floor.contains(&to) || puzzle.blocks.contains(&to) {
+        return None;
+    }
+
+    search(
+        puzzle.player,
+        |player| *player == to,
+        |player, direction| {
+            let move_to = player.step(direction);
+            (floor.contains(&move_to) && !puzzle.blocks.contains(&move_to)).then_some(move_to)
+        },
+    )
+}
+
+pub fn find_push(puzzle: &Puzzle, block: Position, to: Position) -> Option<Vec<Direction>> {
+    let floor: HashSet<Position> = puzzle.floor_positions()?.into_iter().collect();
+    if !puzzle.blocks.contains(&block) || !floor.contains(&to) {
+        return None;
+    }
+    let is_free = |position: Position| {
+        floor.contains(&position) && (position == block || !puzzle.blocks.contains(&position))
+    };
+
+    search(
+        (block, puzzle.player),
+        |(block, _)| *block == to,
+        |(block, player), direction| {
+            let move_to = player.step(direction);
+            if move_to != block {
+                return is_free(move_to).then_some((block, move_to));
+            }
+            let block_to = block.step(direction);
+            is_free(block_to).then_some((block_to, move_to))
+        },
+    )
+}
+
+fn search<S: Copy + Eq + Hash>(
+    start: S,
+    is_done: impl Fn(&S) -> bool,
+    step: impl Fn(S, Direction) -> Option<S>,
+) -> Option<Vec<Direction>> {
+    let mut came_from: HashMap<S, (S, Direction)> = HashMap::new();
+    let mut to_visit = VecDeque::from([start]);
+    while let Some(state) = to_visit.pop_front() {
+        if is_done(&state) {
+            let mut moves = Vec::new();
+            let mut current = state;
+            while current != start {
+                let (previous, direction) = came_from[&current];
+                moves.push(direction);
+                current = previous;
+            }
+            moves.reverse();
+            return Some(moves);
+        }
+
+        for direction in Direction::ALL {
+            let Some(next) = step(state, direction) else {
+                continue;
+            };
+            if next == start || came_from.contains_key(&next) {
+                continue;
+            }
+            came_from.insert(next, (state, direction));
+            to_visit.push_back(next);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fixtures::puzzle, game::Game};
+
+    #[test]
+    fn pushes_a_block_to_the_chosen_cell() {
+        let puzzle = puzzle(&["#######", "#     #", "# $ $ #", "#   @ #", "#######"]);
+        let block = Position { x: 2, y: 2 };
+        let to = Position { x: 2, y: 1 };
+        let moves = find_push(&puzzle, block, to).unwrap();
+
+        let mut game = Game::new(puzzle.clone());
+        for direction in &moves {
+            assert!(game.try_move(*direction).is_legal());
+        }
+        assert!(game.puzzle().blocks.contains(&to));
+        assert!(game.puzzle().blocks.contains(&Position { x: 4, y: 2 }));
+        assert_eq!(game.pushes(), 1);
+        // Around the other block and under this one: left, left, then push up.
+        assert_eq!(moves.len(), 3);
+    }
+
+    #[test]
+    fn finds_no_push_to_a_cell_the_block_cannot_reach() {
+        let puzzle = puzzle(&["######", "#    #", "#$ @ #", "######"]);
+        assert_eq!(
+            find_push(&puzzle, Position { x: 1, y: 2 }, Position { x: 3, y: 1 }),
+            None
+        );
+        assert_eq!(
+            find_push(&puzzle, Position { x: 2, y: 2 }, Position { x: 3, y: 1 }),
+            None
+        );
+        assert_eq!(
+            find_push(&puzzle, Position { x: 1, y: 2 }, Position { x: 0, y: 2 }),
+            None
+        );
+    }
+
+    #[test]
+    fn walks_around_blocks() {
+        let puzzle = puzzle(&["######", "#@$  #", "#    #", "######"]);
+        let to = Position { x: 3, y: 1 };
+        let moves = find_walk(&puzzle, to).unwrap();
+        assert_eq!(moves.len(), 4);
+
+        let mut game = Game::new(puzzle.clone());
+        for direction in &moves {
+            game.try_move(*direction);
+        }
+        assert_eq!(game.player(), to);
+        assert_eq!(game.pushes(), 0);
+        assert_eq!(find_walk(&puzzle, Position { x: 2, y: 1 }), None);
+    }
+}
@@ -1,39 +1,142 @@
+use std::{collections::VecDeque, path::PathBuf};
+
+use bevy::{asset::io::file::FileAssetReader, prelude::*, utils::HashMap};
+use bevy_sokoban::{
+    cell::Layout,
+    game::{Game, MoveResult},
+    history::History,
+    levels::{load_collection, Collection},
+    solver::Puzzle,
+    Direction, Position,
+};
+
 use crate::{
-    level_four, level_one, level_setup, level_three, level_two, GameState, Obstacle, Position,
+    action_plugin::{Action, Actions, Bindings, BufferSettings},
+    click_plugin::is_holding_block,
+    level_setup,
+    save_plugin::SaveSlot,
+    tiles::Translation,
+    GameState,
 };
-use bevy::{prelude::*, utils::HashMap};
 
 pub struct PlayPlugin;
 
-#[derive(Resource, Clone)]
+#[derive(Resource)]
 pub struct LevelState {
     pub current_level: i32,
-    pub obstacles: HashMap<Position, (Entity, Obstacle)>,
-    pub goals: HashMap<Position, Entity>,
-    pub player_position: Position,
+    pub history: History,
+    pub blocks: HashMap<Position, Entity>,
+}
+
+impl LevelState {
+    pub fn new(current_level: i32, game: Game, blocks: HashMap<Position, Entity>) -> LevelState {
+        LevelState {
+            current_level,
+            history: History::new(game),
+            blocks,
+        }
+    }
+
+    pub fn game(&self) -> &Game {
+        self.history.game()
+    }
+
+    pub fn try_move(&mut self, direction: Direction) -> MoveResult {
+        let block_position = self.game().player().step(direction);
+        let result = self.history.try_move(direction);
+        if result == MoveResult::Pushed {
+            if let Some(block_entity) = self.blocks.remove(&block_position) {
+                self.blocks
+                    .insert(block_position.step(direction), block_entity);
+            }
+        }
+        result
+    }
+
+    pub fn undo(&mut self) -> bool {
+        let changed = self.history.undo();
+        self.place_blocks();
+        changed
+    }
+
+    pub fn restart(&mut self) -> bool {
+        let changed = self.history.restart();
+        self.place_blocks();
+        changed
+    }
+
+    // Blocks all look alike, so any moved block entity can take any cell a block moved to.
+    fn place_blocks(&mut self) {
+        let blocks = &self.history.game().puzzle().blocks;
+        let moved: Vec<Position> = self
+            .blocks
+            .keys()
+            .filter(|position| !blocks.contains(position))
+            .copied()
+            .collect();
+        let mut entities: Vec<Entity> = moved
+            .iter()
+            .filter_map(|position| self.blocks.remove(position))
+            .collect();
+        for position in blocks {
+            if !self.blocks.contains_key(position) {
+                if let Some(entity) = entities.pop() {
+                    self.blocks.insert(*position, entity);
+                }
+            }
+        }
+    }
 }
 
 // Remove default implementation and use resource_exists run condition
 impl Default for LevelState {
     fn default() -> Self {
-        Self {
-            current_level: Default::default(),
-            obstacles: Default::default(),
-            goals: Default::default(),
-            player_position: Position { x: 0, y: 0 },
-        }
+        LevelState::new(
+            Default::default(),
+            Game::new(Puzzle {
+                walls: Default::default(),
+                goals: Default::default(),
+                blocks: Default::default(),
+                player: Position { x: 0, y: 0 },
+            }),
+            Default::default(),
+        )
     }
 }
 
-#[derive(Resource, Deref, DerefMut, Default)]
-pub struct UndoStack(pub Vec<LevelState>);
+#[derive(Resource, Default)]
+struct MoveBuffer(VecDeque<Direction>);
+
+#[derive(Resource, Deref, Default)]
+pub struct ActiveCollection {
+    pub name: String,
+    #[deref]
+    pub collection: Collection,
+}
+
+#[derive(Event)]
+pub struct UndoEvent;
 
 #[derive(Event)]
-struct UndoEvent;
+pub struct RestartEvent;
 
 #[derive(Event)]
 pub struct NextLevelEvent(pub i32);
 
+#[derive(Event)]
+pub struct PlaytestEvent(pub Layout);
+
+#[derive(Event)]
+pub struct LevelCompleteEvent {
+    pub level: i32,
+    pub moves: usize,
+    pub pushes: usize,
+    pub lurd: String,
+}
+
+#[derive(Resource)]
+pub struct Playtest;
+
 #[derive(Component)]
 pub struct Player {
     pub is_moving: bool,
@@ -46,92 +149,201 @@ struct Moving {
     to: Position,
 }
 
+impl Moving {
+    fn direction(&self) -> Option<Direction> {
+        Direction::ALL
+            .into_iter()
+            .find(|direction| self.from.step(*direction) == self.to)
+    }
+}
+
+// The move is only played in the game once the animation finishes.
+pub fn start_move(
+    commands: &mut Commands,
+    level_state: &LevelState,
+    player_entity: Entity,
+    player: &mut Player,
+    direction: Direction,
+) -> MoveResult {
+    let result = level_state.game().check_move(direction);
+    if !result.is_legal() {
+        return result;
+    }
+
+    let move_to = level_state.game().player().step(direction);
+    if let Some(block_entity) = level_state.blocks.get(&move_to) {
+        commands.entity(*block_entity).insert(Moving {
+            from: move_to,
+            to: move_to.step(direction),
+        });
+    }
+
+    player.is_moving = true;
+    commands.entity(player_entity).insert(Moving {
+        from: level_state.game().player(),
+        to: move_to,
+    });
+    result
+}
+
+pub fn place_entities(
+    level_state: &LevelState,
+    player_entity: Entity,
+    transform_query: &mut Query<&mut Transform>,
+) {
+    if let Ok(mut player_transform) = transform_query.get_mut(player_entity) {
+        player_transform.translation = level_state.game().player().to_translation();
+    }
+
+    for (position, block_entity) in level_state.blocks.iter() {
+        let Ok(mut block_transform) = transform_query.get_mut(*block_entity) else {
+            continue;
+        };
+        block_transform.translation = position.to_translation();
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 fn handle_input(
     mut commands: Commands,
-    keyboard_input: Res<Input<KeyCode>>,
+    actions: Res<Actions>,
+    buffer_settings: Res<BufferSettings>,
     mut undo_writer: EventWriter<UndoEvent>,
-    level_state: Res<LevelState>,
+    mut restart_writer: EventWriter<RestartEvent>,
+    mut level_state: ResMut<LevelState>,
+    mut move_buffer: ResMut<MoveBuffer>,
     mut player_query: Query<(Entity, &mut Player)>,
 ) {
     let Some((player_entity, mut player)) = player_query.iter_mut().next() else {
         return;
     };
-    if player.is_moving {
+    // Undo and restart also take back a move that is still being animated.
+    if actions.just_pressed(Action::Undo) {
+        undo_writer.send(UndoEvent);
         return;
     }
-
-    if keyboard_input.just_pressed(KeyCode::U) {
-        undo_writer.send(UndoEvent);
+    if actions.just_pressed(Action::Restart) {
+        restart_writer.send(RestartEvent);
         return;
     }
 
-    let mut movement: Option<(i32, i32)> = None;
-    if keyboard_input.pressed(KeyCode::Up) {
-        movement = Some((0, -1));
-    } else if keyboard_input.pressed(KeyCode::Down) {
-        movement = Some((0, 1));
-    } else if keyboard_input.pressed(KeyCode::Left) {
-        movement = Some((-1, 0));
-    } else if keyboard_input.pressed(KeyCode::Right) {
-        movement = Some((1, 0));
+    let is_redoing = actions.pressed(Action::Redo);
+    if is_redoing
+        && !player.is_moving
+        && !level_state.history.is_walking()
+        && level_state.history.redo_walk()
+    {
+        return;
+    }
+    let direction = if is_redoing && level_state.history.is_walking() {
+        None
+    } else if is_redoing {
+        level_state.game().redo_direction()
+    } else {
+        actions.direction()
+    };
+    if direction.is_some() && level_state.history.is_walking() {
+        level_state.history.stop_walk();
     }
+    let is_busy = player.is_moving || level_state.history.is_walking();
 
-    let Some((move_x, move_y)) = movement else {
+    if let Some(pressed) = actions.just_pressed_direction() {
+        let is_queued = is_busy || !move_buffer.0.is_empty();
+        if is_queued && move_buffer.0.len() < buffer_settings.depth {
+            move_buffer.0.push_back(pressed);
+        }
+    }
+    if is_busy {
+        return;
+    }
+    let Some(direction) = move_buffer.0.pop_front().or(direction) else {
         return;
     };
-    let move_to = level_state.player_position.add(move_x, move_y);
+    start_move(
+        &mut commands,
+        &level_state,
+        player_entity,
+        &mut player,
+        direction,
+    );
+}
 
-    match level_state.obstacles.get(&move_to) {
-        Some((_, Obstacle::Wall)) => return,
-        Some((block_entity, Obstacle::Block)) => {
-            let block_move_to = move_to.add(move_x, move_y);
-            if level_state.obstacles.contains_key(&block_move_to) {
-                return;
-            }
-            commands.entity(*block_entity).insert(Moving {
-                from: move_to.clone(),
-                to: block_move_to,
-            });
-        }
-        _ => {}
+fn follow_walk(
+    mut commands: Commands,
+    mut level_state: ResMut<LevelState>,
+    mut player_query: Query<(Entity, &mut Player)>,
+) {
+    if !level_state.history.is_walking() {
+        return;
+    }
+    let Some((player_entity, mut player)) = player_query.iter_mut().next() else {
+        return;
+    };
+    if player.is_moving {
+        return;
     }
 
-    player.is_moving = true;
-    commands.entity(player_entity).insert(Moving {
-        from: level_state.player_position.clone(),
-        to: move_to,
-    });
+    let Some(direction) = level_state.history.next_walk_step() else {
+        return;
+    };
+    let result = start_move(
+        &mut commands,
+        &level_state,
+        player_entity,
+        &mut player,
+        direction,
+    );
+    if !result.is_legal() {
+        level_state.history.end_walk();
+    }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn reset_state(
+    mut commands: Commands,
     mut level_state: ResMut<LevelState>,
-    mut undo_stack: ResMut<UndoStack>,
+    buffer_settings: Res<BufferSettings>,
+    mut move_buffer: ResMut<MoveBuffer>,
     mut undo_reader: EventReader<UndoEvent>,
-    player_query: Query<Entity, With<Player>>,
+    mut restart_reader: EventReader<RestartEvent>,
+    mut player_query: Query<(Entity, &mut Player)>,
+    moving_query: Query<(Entity, &Moving)>,
     mut transform_query: Query<&mut Transform>,
 ) {
-    if undo_reader.read().next().is_some() {
-        let Some(previous_state) = undo_stack.pop() else {
-            return;
-        };
-        *level_state = previous_state;
-
-        let Some(player_entity) = player_query.iter().next() else {
-            return;
-        };
-        let Ok(mut player_transform) = transform_query.get_mut(player_entity) else {
-            return;
-        };
-        player_transform.translation = level_state.player_position.to_translation();
+    let restart = restart_reader.read().count() > 0;
+    if !restart && undo_reader.read().next().is_none() {
+        return;
+    }
+    if buffer_settings.clear_on_undo {
+        move_buffer.0.clear();
+    }
+    let Some((player_entity, mut player)) = player_query.iter_mut().next() else {
+        return;
+    };
 
-        for (position, (obstacle_entity, obstacle)) in level_state.obstacles.iter() {
-            let Obstacle::Block = obstacle else { continue };
-            let Ok(mut block_transform) = transform_query.get_mut(*obstacle_entity) else {
+    // Finish a move that is still being animated, so undo takes it back.
+    if player.is_moving {
+        player.is_moving = false;
+        player.move_timer.reset();
+        for (entity, moving) in moving_query.iter() {
+            commands.entity(entity).remove::<Moving>();
+            if entity != player_entity {
                 continue;
-            };
-            block_transform.translation = position.to_translation();
+            }
+            if let Some(direction) = moving.direction() {
+                level_state.try_move(direction);
+            }
         }
     }
+
+    let changed = if restart {
+        level_state.restart()
+    } else {
+        level_state.undo()
+    };
+    if changed {
+        place_entities(&level_state, player_entity, &mut transform_query);
+    }
 }
 
 // Adapted from: https://github.com/godotengine/godot/blob/27b2260460ab478707d884a16429add5bb3375f1/scene/animation/easing_equations.h
@@ -147,14 +359,19 @@ fn quad_ease_out_v(a: Vec3, b: Vec3, d: f32) -> Vec3 {
     )
 }
 
+#[allow(clippy::too_many_arguments)]
 fn move_objects(
     time: Res<Time>,
     mut commands: Commands,
     mut level_state: ResMut<LevelState>,
-    mut undo_stack: ResMut<UndoStack>,
+    mut move_buffer: ResMut<MoveBuffer>,
     mut player_query: Query<(Entity, &mut Player)>,
     mut moving_query: Query<(Entity, &Moving, &mut Transform)>,
     mut next_level_writer: EventWriter<NextLevelEvent>,
+    mut level_complete_writer: EventWriter<LevelCompleteEvent>,
+    playtest: Option<Res<Playtest>>,
+    current_state: Res<State<GameState>>,
+    mut game_state: ResMut<NextState<GameState>>,
 ) {
     let Some((player_entity, mut player)) = player_query.iter_mut().next() else {
         return;
@@ -175,63 +392,154 @@ fn move_objects(
     } else {
         player.move_timer.reset();
         player.is_moving = false;
-        undo_stack.push(level_state.clone());
+        let mut player_direction = None;
         for (entity, moving, mut transform) in &mut moving_query {
             transform.translation = moving.to.to_translation();
             commands.entity(entity).remove::<Moving>();
             if entity == player_entity {
-                level_state.player_position = moving.to;
+                player_direction = moving.direction();
             }
-
-            let Some(obstacle) = level_state.obstacles.remove(&moving.from) else {
-                continue;
-            };
-            level_state.obstacles.insert(moving.to, obstacle);
         }
+        let Some(direction) = player_direction else {
+            return;
+        };
+        level_state.try_move(direction);
 
-        let has_won = level_state
-            .goals
-            .iter()
-            .all(|(goal_position, _)| level_state.obstacles.contains_key(goal_position));
-        if has_won {
+        // A replay reports the result itself instead of moving on.
+        if !level_state.game().is_won() || *current_state.get() == GameState::Replaying {
+            // A clicked path has to end first, so buffered moves aren't undone along with it.
+            if level_state.history.is_walking() {
+                return;
+            }
+            while let Some(direction) = move_buffer.0.pop_front() {
+                let result = start_move(
+                    &mut commands,
+                    &level_state,
+                    player_entity,
+                    &mut player,
+                    direction,
+                );
+                if result.is_legal() {
+                    break;
+                }
+            }
+            return;
+        }
+        move_buffer.0.clear();
+        if playtest.is_some() {
+            game_state.set(GameState::Editing);
+        } else {
+            level_complete_writer.send(LevelCompleteEvent {
+                level: level_state.current_level,
+                moves: level_state.game().moves(),
+                pushes: level_state.game().pushes(),
+                lurd: level_state.game().lurd().to_string(),
+            });
             next_level_writer.send(NextLevelEvent(level_state.current_level + 1));
         }
     }
 }
 
+fn clear_move_buffer(mut move_buffer: ResMut<MoveBuffer>) {
+    move_buffer.0.clear();
+}
+
+fn levels_directory() -> PathBuf {
+    FileAssetReader::get_base_path()
+        .join("assets")
+        .join("levels")
+}
+
+fn load_active_collection(mut commands: Commands) {
+    let path = std::env::args()
+        .nth(1)
+        .map(PathBuf::from)
+        .unwrap_or_else(|| levels_directory().join("starter.sok"));
+    match load_collection(&path) {
+        Ok(collection) => commands.insert_resource(ActiveCollection {
+            name: path
+                .file_stem()
+                .map_or_else(String::new, |stem| stem.to_string_lossy().into_owned()),
+            collection,
+        }),
+        Err(error) => error!("Could not load collection {}: {}", path.display(), error),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 fn load_next_level(
     mut commands: Commands,
     almost_everything_query: Query<Entity, Without<Window>>,
     asset_server: Res<AssetServer>,
+    active_collection: Res<ActiveCollection>,
+    save_slot: Res<SaveSlot>,
     mut next_level_reader: EventReader<NextLevelEvent>,
+    mut playtest_reader: EventReader<PlaytestEvent>,
+    mut game_state: ResMut<NextState<GameState>>,
 ) {
-    let Some(next_level) = next_level_reader.read().next() else {
+    let (level, level_layout) = if let Some(playtest) = playtest_reader.read().next() {
+        (0, playtest.0.clone())
+    } else if let Some(next_level) = next_level_reader.read().next() {
+        let level_entry = usize::try_from(next_level.0 - 1)
+            .ok()
+            .and_then(|level_index| active_collection.levels.get(level_index));
+        let Some(level_entry) = level_entry else {
+            game_state.set(GameState::CollectionComplete);
+            return;
+        };
+        (next_level.0, level_entry.layout.clone())
+    } else {
         return;
     };
+
     for entity in almost_everything_query.iter() {
         commands.entity(entity).despawn();
     }
 
-    let next_level_layout = match next_level.0 {
-        1 => level_one(),
-        2 => level_two(),
-        3 => level_three(),
-        4 => level_four(),
-        _ => panic!("Level not found"),
-    };
-    level_setup(commands, asset_server, next_level.0, next_level_layout);
+    let moves = save_slot
+        .moves_in_progress(&active_collection.name, level)
+        .unwrap_or_default();
+    level_setup(commands, asset_server, level, level_layout, moves);
 }
 
-fn pause_game(
-    mut keyboard_input: ResMut<Input<KeyCode>>,
+fn show_collection_complete(
+    mut commands: Commands,
+    active_collection: Res<ActiveCollection>,
+    bindings: Res<Bindings>,
+) {
+    let title = active_collection.title.as_deref().unwrap_or("Collection");
+    commands.spawn(TextBundle::from_section(
+        format!(
+            "{} complete!\nPress {} to play again",
+            title,
+            bindings.key_label(Action::Pause)
+        ),
+        TextStyle {
+            font_size: 24.0,
+            color: Color::WHITE,
+            ..default()
+        },
+    ));
+}
+
+fn restart_collection(
+    mut actions: ResMut<Actions>,
+    mut next_level_writer: EventWriter<NextLevelEvent>,
     mut game_state: ResMut<NextState<GameState>>,
 ) {
-    if keyboard_input.just_pressed(KeyCode::Space) {
-        keyboard_input.reset(KeyCode::Space);
+    if actions.just_pressed(Action::Pause) {
+        actions.reset(Action::Pause);
+        next_level_writer.send(NextLevelEvent(1));
+        game_state.set(GameState::Playing);
+    }
+}
+
+fn pause_game(mut actions: ResMut<Actions>, mut game_state: ResMut<NextState<GameState>>) {
+    if actions.just_pressed(Action::Pause) {
+        actions.reset(Action::Pause);
         game_state.set(GameState::Paused);
-        return;
-    } else if keyboard_input.just_pressed(KeyCode::E) {
-        keyboard_input.reset(KeyCode::E);
+    } else if actions.just_pressed(Action::ToggleEditor) {
+        actions.reset(Action::ToggleEditor);
         game_state.set(GameState::Editing);
     }
 }
@@ -239,19 +547,40 @@ fn pause_game(
 impl Plugin for PlayPlugin {
     fn build(&self, app: &mut App) {
         app.add_event::<UndoEvent>()
+            .add_event::<RestartEvent>()
             .add_event::<NextLevelEvent>()
+            .add_event::<PlaytestEvent>()
+            .add_event::<LevelCompleteEvent>()
             .insert_resource(LevelState::default())
-            .insert_resource(UndoStack::default())
+            .insert_resource(MoveBuffer::default())
+            .insert_resource(ActiveCollection::default())
+            .add_systems(Startup, load_active_collection)
+            .add_systems(OnExit(GameState::Playing), clear_move_buffer)
+            .add_systems(
+                OnEnter(GameState::CollectionComplete),
+                show_collection_complete,
+            )
+            .add_systems(
+                Update,
+                restart_collection.run_if(in_state(GameState::CollectionComplete)),
+            )
             .add_systems(
                 Update,
                 (
                     pause_game,
-                    handle_input.after(pause_game),
-                    reset_state.after(handle_input),
-                    move_objects.after(handle_input),
+                    follow_walk.before(handle_input),
+                    handle_input.after(pause_game).run_if(not(is_holding_block)),
                     load_next_level.after(move_objects),
                 )
                     .run_if(in_state(GameState::Playing)),
+            )
+            .add_systems(
+                Update,
+                (
+                    reset_state.after(handle_input),
+                    move_objects.after(handle_input),
+                )
+                    .run_if(in_state(GameState::Playing).or_else(in_state(GameState::Replaying))),
             );
     }
 }
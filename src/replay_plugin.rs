@@ -0,0 +1,294 @@
+use std::{fs, time::Duration};
+
+use bevy::prelude::*;
+
+use bevy_sokoban::{game::MoveResult, Direction};
+
+use crate::{
+    action_plugin::{Action, Actions, Bindings},
+    play_plugin::{place_entities, start_move, ActiveCollection, LevelState, Player, UndoEvent},
+    solution_plugin::{solution_path, LastSolution},
+    GameState, MOVE_SECONDS,
+};
+
+pub struct ReplayPlugin;
+
+const MIN_SPEED: f32 = 0.25;
+const MAX_SPEED: f32 = 8.0;
+
+#[derive(Resource)]
+struct Replay {
+    steps: Vec<(Direction, bool)>,
+    next_step: usize,
+    is_playing: bool,
+    speed: f32,
+    error: Option<String>,
+}
+
+#[derive(Component)]
+struct ReplayText;
+
+fn parse_lurd(lurd: &str) -> Result<Vec<(Direction, bool)>, String> {
+    lurd.chars()
+        .filter(|character| !character.is_whitespace())
+        .enumerate()
+        .map(|(index, character)| {
+            Direction::from_lurd(character).ok_or_else(|| {
+                format!(
+                    "Step {} ('{}') is not a LURD character",
+                    index + 1,
+                    character
+                )
+            })
+        })
+        .collect()
+}
+
+// Prefers an exported solution, then the last completed run, then the moves made so far.
+fn find_solution(
+    collection_name: &str,
+    level_state: &LevelState,
+    last_solution: &LastSolution,
+) -> Option<String> {
+    if let Ok(lurd) = fs::read_to_string(solution_path(collection_name, level_state.current_level))
+    {
+        return Some(lurd);
+    }
+    if last_solution.level == level_state.current_level && !last_solution.lurd.is_empty() {
+        return Some(last_solution.lurd.clone());
+    }
+    if !level_state.game().lurd().is_empty() {
+        return Some(level_state.game().lurd().to_string());
+    }
+    None
+}
+
+#[allow(clippy::too_many_arguments)]
+fn start_replay(
+    mut commands: Commands,
+    mut actions: ResMut<Actions>,
+    active_collection: Res<ActiveCollection>,
+    last_solution: Res<LastSolution>,
+    mut level_state: ResMut<LevelState>,
+    player_query: Query<(Entity, &Player)>,
+    mut transform_query: Query<&mut Transform>,
+    mut game_state: ResMut<NextState<GameState>>,
+) {
+    if !actions.just_pressed(Action::Replay) {
+        return;
+    }
+    let Some((player_entity, player)) = player_query.iter().next() else {
+        return;
+    };
+    if player.is_moving {
+        return;
+    }
+    actions.reset(Action::Replay);
+
+    let (steps, error) = match find_solution(&active_collection.name, &level_state, &last_solution)
+    {
+        Some(lurd) => match parse_lurd(&lurd) {
+            Ok(steps) => (steps, None),
+            Err(error) => (Vec::new(), Some(error)),
+        },
+        None => (
+            Vec::new(),
+            Some("There is no recorded solution for this level".to_string()),
+        ),
+    };
+
+    level_state.restart();
+    place_entities(&level_state, player_entity, &mut transform_query);
+
+    commands.insert_resource(Replay {
+        steps,
+        next_step: 0,
+        is_playing: error.is_none(),
+        speed: 1.0,
+        error,
+    });
+    game_state.set(GameState::Replaying);
+}
+
+// An illegal step stops the replay before it is animated.
+fn take_step(
+    commands: &mut Commands,
+    level_state: &LevelState,
+    player_entity: Entity,
+    player: &mut Player,
+    (direction, is_push): (Direction, bool),
+) -> Result<(), String> {
+    let lurd = direction.to_lurd(is_push);
+    match level_state.game().check_move(direction) {
+        MoveResult::HitWall => Err(format!("'{}' walks into a wall", lurd)),
+        MoveResult::BlockStuck => Err(format!("'{}' pushes a block into an obstacle", lurd)),
+        MoveResult::Moved if is_push => Err(format!(
+            "'{}' is a push but there is no block to push",
+            lurd
+        )),
+        MoveResult::Pushed if !is_push => {
+            Err(format!("'{}' is a move but would push a block", lurd))
+        }
+        _ => {
+            start_move(commands, level_state, player_entity, player, direction);
+            Ok(())
+        }
+    }
+}
+
+fn drive_replay(
+    mut commands: Commands,
+    mut actions: ResMut<Actions>,
+    mut replay: ResMut<Replay>,
+    level_state: Res<LevelState>,
+    mut player_query: Query<(Entity, &mut Player)>,
+    mut undo_writer: EventWriter<UndoEvent>,
+    mut game_state: ResMut<NextState<GameState>>,
+) {
+    if actions.just_pressed(Action::Replay) {
+        actions.reset(Action::Replay);
+        game_state.set(GameState::Playing);
+        return;
+    }
+    if actions.just_pressed(Action::Pause) {
+        replay.is_playing = !replay.is_playing;
+    }
+    if actions.just_pressed(Action::MoveUp) {
+        replay.speed = (replay.speed * 2.0).min(MAX_SPEED);
+    } else if actions.just_pressed(Action::MoveDown) {
+        replay.speed = (replay.speed / 2.0).max(MIN_SPEED);
+    }
+
+    let Some((player_entity, mut player)) = player_query.iter_mut().next() else {
+        return;
+    };
+    if player.is_moving {
+        return;
+    }
+
+    if actions.just_pressed(Action::MoveLeft) {
+        replay.is_playing = false;
+        if replay.next_step > 0 {
+            replay.next_step -= 1;
+            replay.error = None;
+            undo_writer.send(UndoEvent);
+        }
+        return;
+    }
+
+    let step_forward = actions.just_pressed(Action::MoveRight);
+    if step_forward {
+        replay.is_playing = false;
+    }
+    if !(step_forward || replay.is_playing) || replay.error.is_some() {
+        return;
+    }
+    let Some(&step) = replay.steps.get(replay.next_step) else {
+        replay.is_playing = false;
+        return;
+    };
+
+    player
+        .move_timer
+        .set_duration(Duration::from_secs_f32(MOVE_SECONDS / replay.speed));
+    match take_step(
+        &mut commands,
+        &level_state,
+        player_entity,
+        &mut player,
+        step,
+    ) {
+        Ok(()) => replay.next_step += 1,
+        Err(error) => {
+            replay.is_playing = false;
+            replay.error = Some(format!("Step {}: {}", replay.next_step + 1, error));
+        }
+    }
+}
+
+fn update_replay_text(
+    mut commands: Commands,
+    replay: Res<Replay>,
+    level_state: Res<LevelState>,
+    bindings: Res<Bindings>,
+    mut text_query: Query<&mut Text, With<ReplayText>>,
+) {
+    let status = if let Some(error) = &replay.error {
+        format!("Stopped. {}", error)
+    } else if replay.next_step == replay.steps.len() {
+        if level_state.game().is_won() {
+            "Finished, the level is solved".to_string()
+        } else {
+            "Finished, but the level is not solved".to_string()
+        }
+    } else if replay.is_playing {
+        "Playing".to_string()
+    } else {
+        "Paused".to_string()
+    };
+    let value = format!(
+        "Replay step {}/{} at {}x speed\n{}\n{}: play/pause, {}/{}: step, {}/{}: speed, {}: play from here",
+        replay.next_step,
+        replay.steps.len(),
+        replay.speed,
+        status,
+        bindings.key_label(Action::Pause),
+        bindings.key_label(Action::MoveLeft),
+        bindings.key_label(Action::MoveRight),
+        bindings.key_label(Action::MoveUp),
+        bindings.key_label(Action::MoveDown),
+        bindings.key_label(Action::Replay)
+    );
+
+    let Some(mut text) = text_query.iter_mut().next() else {
+        commands.spawn((
+            ReplayText,
+            TextBundle::from_section(
+                value,
+                TextStyle {
+                    font_size: 14.0,
+                    color: Color::WHITE,
+                    ..default()
+                },
+            )
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(5.0),
+                left: Val::Px(5.0),
+                ..default()
+            }),
+        ));
+        return;
+    };
+    if text.sections[0].value != value {
+        text.sections[0].value = value;
+    }
+}
+
+fn finish_replay(
+    mut commands: Commands,
+    mut player_query: Query<&mut Player>,
+    text_query: Query<Entity, With<ReplayText>>,
+) {
+    commands.remove_resource::<Replay>();
+    for mut player in player_query.iter_mut() {
+        player
+            .move_timer
+            .set_duration(Duration::from_secs_f32(MOVE_SECONDS));
+    }
+    for entity in text_query.iter() {
+        commands.entity(entity).despawn();
+    }
+}
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, start_replay.run_if(in_state(GameState::Playing)))
+            .add_systems(
+                Update,
+                (drive_replay, update_replay_text.after(drive_replay))
+                    .run_if(in_state(GameState::Replaying)),
+            )
+            .add_systems(OnExit(GameState::Replaying), finish_replay);
+    }
+}
@@ -0,0 +1,189 @@
+use std::{fs, io, path::PathBuf};
+
+use bevy::{prelude::*, utils::HashSet};
+use bevy_sokoban::storage::data_directory;
+
+use crate::{
+    play_plugin::{ActiveCollection, LevelCompleteEvent, LevelState, Playtest},
+    score_plugin::{level_key, BestScores},
+    GameState,
+};
+
+pub struct SavePlugin;
+
+pub const SLOT_COUNT: usize = 3;
+
+pub struct InProgress {
+    pub collection_name: String,
+    pub level: i32,
+    pub lurd: String,
+}
+
+#[derive(Resource, Default)]
+pub struct SaveSlot {
+    pub number: usize,
+    pub unlocked: HashSet<String>,
+    pub in_progress: Option<InProgress>,
+}
+
+pub fn slot_directory(number: usize) -> PathBuf {
+    data_directory()
+        .join("saves")
+        .join(format!("slot-{}", number))
+}
+
+fn active_slot_path() -> PathBuf {
+    data_directory().join("saves").join("active-slot.txt")
+}
+
+impl SaveSlot {
+    fn path(number: usize) -> PathBuf {
+        slot_directory(number).join("progress.txt")
+    }
+
+    pub fn load(number: usize) -> SaveSlot {
+        let mut save_slot = SaveSlot {
+            number,
+            ..default()
+        };
+        let Ok(text) = fs::read_to_string(SaveSlot::path(number)) else {
+            return save_slot;
+        };
+
+        for line in text.lines() {
+            let fields: Vec<&str> = line.split('\t').collect();
+            match fields[..] {
+                ["unlocked", key] => {
+                    save_slot.unlocked.insert(key.to_string());
+                }
+                ["in-progress", collection_name, level, lurd] => {
+                    let Ok(level) = level.parse() else {
+                        continue;
+                    };
+                    save_slot.in_progress = Some(InProgress {
+                        collection_name: collection_name.to_string(),
+                        level,
+                        lurd: lurd.to_string(),
+                    });
+                }
+                _ => {}
+            }
+        }
+        save_slot
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let mut keys: Vec<&String> = self.unlocked.iter().collect();
+        keys.sort();
+
+        let mut text = String::new();
+        for key in keys {
+            text.push_str(&format!("unlocked\t{}\n", key));
+        }
+        if let Some(in_progress) = &self.in_progress {
+            text.push_str(&format!(
+                "in-progress\t{}\t{}\t{}\n",
+                in_progress.collection_name, in_progress.level, in_progress.lurd
+            ));
+        }
+
+        fs::create_dir_all(slot_directory(self.number))?;
+        fs::write(SaveSlot::path(self.number), text)
+    }
+
+    pub fn moves_in_progress(&self, collection_name: &str, level: i32) -> Option<&str> {
+        self.in_progress
+            .as_ref()
+            .filter(|in_progress| {
+                in_progress.collection_name == collection_name && in_progress.level == level
+            })
+            .map(|in_progress| in_progress.lurd.as_str())
+    }
+}
+
+fn load_active_slot(mut commands: Commands) {
+    let number = fs::read_to_string(active_slot_path())
+        .ok()
+        .and_then(|text| text.trim().parse().ok())
+        .filter(|number| (1..=SLOT_COUNT).contains(number))
+        .unwrap_or(1);
+    commands.insert_resource(SaveSlot::load(number));
+    commands.insert_resource(BestScores::load(number));
+}
+
+fn switch_slot(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut save_slot: ResMut<SaveSlot>,
+    mut best_scores: ResMut<BestScores>,
+) {
+    let keys = [KeyCode::Key1, KeyCode::Key2, KeyCode::Key3];
+    let Some(number) =
+        (1..=SLOT_COUNT).find(|number| keyboard_input.just_pressed(keys[number - 1]))
+    else {
+        return;
+    };
+    if number == save_slot.number {
+        return;
+    }
+
+    *save_slot = SaveSlot::load(number);
+    *best_scores = BestScores::load(number);
+    let written = fs::create_dir_all(data_directory().join("saves"))
+        .and_then(|_| fs::write(active_slot_path(), format!("{}\n", number)));
+    if let Err(error) = written {
+        error!("Could not remember the active save slot: {}", error);
+    }
+}
+
+fn save_progress(save_slot: &SaveSlot) {
+    if let Err(error) = save_slot.save() {
+        error!("Could not save progress: {}", error);
+    }
+}
+
+fn track_progress(
+    level_state: Res<LevelState>,
+    active_collection: Res<ActiveCollection>,
+    playtest: Option<Res<Playtest>>,
+    mut save_slot: ResMut<SaveSlot>,
+) {
+    let level = level_state.current_level;
+    if !level_state.is_changed() || playtest.is_some() || level < 1 || level_state.game().is_won() {
+        return;
+    }
+
+    let collection_name = &active_collection.name;
+    save_slot.unlocked.insert(level_key(collection_name, level));
+    save_slot.in_progress = Some(InProgress {
+        collection_name: collection_name.clone(),
+        level,
+        lurd: level_state.game().lurd().to_string(),
+    });
+    save_progress(&save_slot);
+}
+
+// Winning the last level leaves the playing state straight away, so wins come from the event.
+fn record_win(
+    active_collection: Res<ActiveCollection>,
+    mut save_slot: ResMut<SaveSlot>,
+    mut level_complete_reader: EventReader<LevelCompleteEvent>,
+) {
+    for level_complete in level_complete_reader.read() {
+        let collection_name = &active_collection.name;
+        for level in [level_complete.level, level_complete.level + 1] {
+            save_slot.unlocked.insert(level_key(collection_name, level));
+        }
+        save_slot.in_progress = None;
+        save_progress(&save_slot);
+    }
+}
+
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(SaveSlot::default())
+            .add_systems(Startup, load_active_slot)
+            .add_systems(Update, switch_slot.run_if(in_state(GameState::LevelSelect)))
+            .add_systems(Update, track_progress.run_if(in_state(GameState::Playing)))
+            .add_systems(Update, record_win);
+    }
+}
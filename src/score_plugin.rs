@@ -0,0 +1,279 @@
+use std::{fs, io, path::PathBuf};
+
+use bevy::{prelude::*, utils::HashMap};
+
+use crate::{
+    play_plugin::{ActiveCollection, LevelCompleteEvent, LevelState},
+    save_plugin::slot_directory,
+    GameState,
+};
+
+pub struct ScorePlugin;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Score {
+    pub moves: usize,
+    pub pushes: usize,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct BestScore {
+    pub fewest_moves: Score,
+    pub fewest_pushes: Score,
+}
+
+#[derive(Resource, Default)]
+pub struct BestScores {
+    slot: usize,
+    scores: HashMap<String, BestScore>,
+}
+
+impl BestScores {
+    fn path(slot: usize) -> PathBuf {
+        slot_directory(slot).join("scores.txt")
+    }
+
+    pub fn load(slot: usize) -> BestScores {
+        let text = fs::read_to_string(BestScores::path(slot)).unwrap_or_default();
+        BestScores::parse(slot, &text)
+    }
+
+    fn parse(slot: usize, text: &str) -> BestScores {
+        let mut best_scores = HashMap::default();
+        for line in text.lines() {
+            let fields: Vec<&str> = line.split('\t').collect();
+            let [key, moves_moves, moves_pushes, pushes_moves, pushes_pushes] = fields[..] else {
+                continue;
+            };
+            let numbers: Result<Vec<usize>, _> =
+                [moves_moves, moves_pushes, pushes_moves, pushes_pushes]
+                    .iter()
+                    .map(|field| field.parse())
+                    .collect();
+            let Ok(numbers) = numbers else {
+                continue;
+            };
+
+            best_scores.insert(
+                key.to_string(),
+                BestScore {
+                    fewest_moves: Score {
+                        moves: numbers[0],
+                        pushes: numbers[1],
+                    },
+                    fewest_pushes: Score {
+                        moves: numbers[2],
+                        pushes: numbers[3],
+                    },
+                },
+            );
+        }
+        BestScores {
+            slot,
+            scores: best_scores,
+        }
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        fs::create_dir_all(slot_directory(self.slot))?;
+        fs::write(BestScores::path(self.slot), self.to_text())
+    }
+
+    fn to_text(&self) -> String {
+        let mut keys: Vec<&String> = self.scores.keys().collect();
+        keys.sort();
+
+        let mut text = String::new();
+        for key in keys {
+            let best_score = self.scores[key];
+            text.push_str(&format!(
+                "{}\t{}\t{}\t{}\t{}\n",
+                key,
+                best_score.fewest_moves.moves,
+                best_score.fewest_moves.pushes,
+                best_score.fewest_pushes.moves,
+                best_score.fewest_pushes.pushes
+            ));
+        }
+        text
+    }
+
+    pub fn get(&self, key: &str) -> Option<&BestScore> {
+        self.scores.get(key)
+    }
+
+    // Ties on the main count go to the other count.
+    pub fn record(&mut self, key: &str, score: Score) -> bool {
+        let Some(best_score) = self.scores.get_mut(key) else {
+            self.scores.insert(
+                key.to_string(),
+                BestScore {
+                    fewest_moves: score,
+                    fewest_pushes: score,
+                },
+            );
+            return true;
+        };
+
+        let mut improved = false;
+        if (score.moves, score.pushes)
+            < (
+                best_score.fewest_moves.moves,
+                best_score.fewest_moves.pushes,
+            )
+        {
+            best_score.fewest_moves = score;
+            improved = true;
+        }
+        if (score.pushes, score.moves)
+            < (
+                best_score.fewest_pushes.pushes,
+                best_score.fewest_pushes.moves,
+            )
+        {
+            best_score.fewest_pushes = score;
+            improved = true;
+        }
+        improved
+    }
+}
+
+pub fn level_key(collection_name: &str, level: i32) -> String {
+    format!("{}:{}", collection_name, level)
+}
+
+#[derive(Component)]
+struct ScoreText;
+
+fn record_score(
+    active_collection: Res<ActiveCollection>,
+    mut best_scores: ResMut<BestScores>,
+    mut level_complete_reader: EventReader<LevelCompleteEvent>,
+) {
+    for level_complete in level_complete_reader.read() {
+        let score = Score {
+            moves: level_complete.moves,
+            pushes: level_complete.pushes,
+        };
+        let key = level_key(&active_collection.name, level_complete.level);
+        if !best_scores.record(&key, score) {
+            continue;
+        }
+        if let Err(error) = best_scores.save() {
+            error!("Could not save best scores: {}", error);
+        }
+    }
+}
+
+fn update_score_text(
+    mut commands: Commands,
+    level_state: Res<LevelState>,
+    active_collection: Res<ActiveCollection>,
+    best_scores: Res<BestScores>,
+    mut text_query: Query<&mut Text, With<ScoreText>>,
+) {
+    let mut value = format!(
+        "Moves: {}  Pushes: {}",
+        level_state.game().moves(),
+        level_state.game().pushes()
+    );
+    let key = level_key(&active_collection.name, level_state.current_level);
+    if let Some(best_score) = best_scores.get(&key) {
+        value.push_str(&format!(
+            "\nBest: {}/{} (moves), {}/{} (pushes)",
+            best_score.fewest_moves.moves,
+            best_score.fewest_moves.pushes,
+            best_score.fewest_pushes.moves,
+            best_score.fewest_pushes.pushes
+        ));
+    }
+
+    let Some(mut text) = text_query.iter_mut().next() else {
+        commands.spawn((
+            ScoreText,
+            TextBundle::from_section(
+                value,
+                TextStyle {
+                    font_size: 14.0,
+                    color: Color::WHITE,
+                    ..default()
+                },
+            )
+            .with_text_alignment(TextAlignment::Right)
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(5.0),
+                right: Val::Px(5.0),
+                ..default()
+            }),
+        ));
+        return;
+    };
+    if text.sections[0].value != value {
+        text.sections[0].value = value;
+    }
+}
+
+impl Plugin for ScorePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(BestScores::default())
+            .add_systems(Update, record_score)
+            .add_systems(
+                Update,
+                update_score_text
+                    .run_if(in_state(GameState::Playing).or_else(in_state(GameState::Replaying))),
+            );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn score(moves: usize, pushes: usize) -> Score {
+        Score { moves, pushes }
+    }
+
+    #[test]
+    fn only_a_better_score_replaces_a_record() {
+        let mut best_scores = BestScores::default();
+        assert!(best_scores.record("pack:1", score(20, 5)));
+        assert!(!best_scores.record("pack:1", score(20, 5)));
+        assert!(!best_scores.record("pack:1", score(25, 6)));
+
+        assert!(best_scores.record("pack:1", score(18, 7)));
+        let best_score = best_scores.get("pack:1").unwrap();
+        assert_eq!(best_score.fewest_moves, score(18, 7));
+        assert_eq!(best_score.fewest_pushes, score(20, 5));
+
+        // A tie on moves is broken by pushes.
+        assert!(best_scores.record("pack:1", score(18, 6)));
+        assert_eq!(
+            best_scores.get("pack:1").unwrap().fewest_moves,
+            score(18, 6)
+        );
+    }
+
+    #[test]
+    fn round_trips_through_text() {
+        let mut best_scores = BestScores::default();
+        best_scores.record("pack:2", score(30, 9));
+        best_scores.record("pack:1", score(20, 5));
+        best_scores.record("pack:1", score(18, 7));
+        let text = best_scores.to_text();
+        assert_eq!(text, "pack:1\t18\t7\t20\t5\npack:2\t30\t9\t30\t9\n");
+
+        let loaded = BestScores::parse(2, &text);
+        assert_eq!(loaded.slot, 2);
+        assert_eq!(loaded.to_text(), text);
+    }
+
+    #[test]
+    fn skips_malformed_lines() {
+        let text = "pack:1\t18\t7\t20\t5\npack:2\t30\t9\npack:3\tmany\t1\t2\t3\n\n";
+        let best_scores = BestScores::parse(1, text);
+        assert!(best_scores.get("pack:1").is_some());
+        assert!(best_scores.get("pack:2").is_none());
+        assert!(best_scores.get("pack:3").is_none());
+    }
+}
@@ -0,0 +1,255 @@
+use bevy::prelude::*;
+
+use crate::{
+    action_plugin::{Action, Actions, Binding, Bindings, BufferSettings, KEYS, MAX_BUFFER_DEPTH},
+    gamepad_plugin::{GamepadInput, BUTTONS},
+    GameState,
+};
+
+pub struct SettingsPlugin;
+
+#[derive(Resource, Default)]
+struct Settings {
+    selected: usize,
+    is_capturing: bool,
+    is_confirming_defaults: bool,
+}
+
+#[derive(Component)]
+struct SettingsList;
+
+// The rows after the actions.
+const BUFFER_DEPTH_ROW: usize = Action::ALL.len();
+const CLEAR_ON_UNDO_ROW: usize = Action::ALL.len() + 1;
+const ROW_COUNT: usize = Action::ALL.len() + 2;
+
+fn open_settings(mut actions: ResMut<Actions>, mut game_state: ResMut<NextState<GameState>>) {
+    if actions.just_pressed(Action::Settings) {
+        actions.reset(Action::Settings);
+        game_state.set(GameState::Settings);
+    }
+}
+
+fn show_settings(mut commands: Commands, almost_everything_query: Query<Entity, Without<Window>>) {
+    for entity in almost_everything_query.iter() {
+        commands.entity(entity).despawn();
+    }
+    commands.spawn(Camera2dBundle::default());
+    commands.insert_resource(Settings::default());
+}
+
+fn save_bindings(bindings: &Bindings) {
+    if let Err(error) = bindings.save() {
+        error!("Could not save the key bindings: {}", error);
+    }
+}
+
+fn save_buffer_settings(buffer_settings: &BufferSettings) {
+    if let Err(error) = buffer_settings.save() {
+        error!("Could not save the input settings: {}", error);
+    }
+}
+
+fn edit_bindings(
+    mut keyboard_input: ResMut<Input<KeyCode>>,
+    gamepad_input: Res<GamepadInput>,
+    mut actions: ResMut<Actions>,
+    mut settings: ResMut<Settings>,
+    mut bindings: ResMut<Bindings>,
+    mut buffer_settings: ResMut<BufferSettings>,
+    mut game_state: ResMut<NextState<GameState>>,
+) {
+    if settings.is_capturing {
+        // Escape would otherwise quit the game.
+        if keyboard_input.just_pressed(KeyCode::Escape) {
+            keyboard_input.reset(KeyCode::Escape);
+            settings.is_capturing = false;
+            return;
+        }
+
+        let action = Action::ALL[settings.selected];
+        let binding = KEYS
+            .into_iter()
+            .find(|key| keyboard_input.just_pressed(*key))
+            .map(Binding::Key)
+            .or_else(|| {
+                BUTTONS
+                    .into_iter()
+                    .find(|button| gamepad_input.buttons.just_pressed(*button))
+                    .map(Binding::Button)
+            });
+        if let Some(binding) = binding {
+            bindings.add(action, binding);
+            save_bindings(&bindings);
+            settings.is_capturing = false;
+        }
+        return;
+    }
+
+    // Only leaving goes through the bindings, so a broken binding can always be fixed here.
+    if settings.is_confirming_defaults {
+        if keyboard_input.just_pressed(KeyCode::Y) {
+            *bindings = Bindings::default();
+            save_bindings(&bindings);
+            *buffer_settings = BufferSettings::default();
+            save_buffer_settings(&buffer_settings);
+            settings.is_confirming_defaults = false;
+        } else if keyboard_input.just_pressed(KeyCode::N) {
+            settings.is_confirming_defaults = false;
+        }
+        return;
+    }
+
+    if keyboard_input.just_pressed(KeyCode::Up) {
+        settings.selected = settings.selected.saturating_sub(1);
+    } else if keyboard_input.just_pressed(KeyCode::Down) {
+        settings.selected = (settings.selected + 1).min(ROW_COUNT - 1);
+    } else if settings.selected == BUFFER_DEPTH_ROW {
+        let depth = buffer_settings.depth;
+        if keyboard_input.just_pressed(KeyCode::Left) && depth > 0 {
+            buffer_settings.depth = depth - 1;
+            save_buffer_settings(&buffer_settings);
+        } else if keyboard_input.just_pressed(KeyCode::Right) && depth < MAX_BUFFER_DEPTH {
+            buffer_settings.depth = depth + 1;
+            save_buffer_settings(&buffer_settings);
+        }
+    } else if settings.selected == CLEAR_ON_UNDO_ROW {
+        if keyboard_input.just_pressed(KeyCode::Return) {
+            buffer_settings.clear_on_undo = !buffer_settings.clear_on_undo;
+            save_buffer_settings(&buffer_settings);
+        }
+    } else if keyboard_input.just_pressed(KeyCode::Return) {
+        settings.is_capturing = true;
+    } else if keyboard_input.just_pressed(KeyCode::Back) {
+        bindings.clear(Action::ALL[settings.selected]);
+        save_bindings(&bindings);
+    }
+
+    if keyboard_input.just_pressed(KeyCode::D) {
+        settings.is_confirming_defaults = true;
+    } else if actions.just_pressed(Action::Settings) {
+        actions.reset(Action::Settings);
+        game_state.set(GameState::LevelSelect);
+    }
+}
+
+fn draw_settings(
+    mut commands: Commands,
+    settings: Res<Settings>,
+    bindings: Res<Bindings>,
+    buffer_settings: Res<BufferSettings>,
+    list_query: Query<Entity, With<SettingsList>>,
+) {
+    let is_changed = settings.is_changed() || bindings.is_changed() || buffer_settings.is_changed();
+    if !is_changed && !list_query.is_empty() {
+        return;
+    }
+    for entity in list_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    let instructions = if settings.is_capturing {
+        format!(
+            "Press a key or gamepad button for {}, Escape to cancel",
+            Action::ALL[settings.selected].description()
+        )
+    } else if settings.is_confirming_defaults {
+        "Reset every control and input setting to its default? (Y/N)".to_string()
+    } else {
+        format!(
+            "Up/Down choose, Enter add a key or button or toggle, Left/Right change a number, \
+             Backspace clear, D defaults, {} back",
+            bindings.key_label(Action::Settings)
+        )
+    };
+
+    commands
+        .spawn((
+            SettingsList,
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    padding: UiRect::all(Val::Px(10.0)),
+                    row_gap: Val::Px(2.0),
+                    ..default()
+                },
+                ..default()
+            },
+        ))
+        .with_children(|list| {
+            list.spawn(TextBundle::from_section(
+                format!("Controls\n{}", instructions),
+                TextStyle {
+                    font_size: 16.0,
+                    color: Color::WHITE,
+                    ..default()
+                },
+            ));
+
+            let row_color = |index: usize| {
+                if index == settings.selected {
+                    Color::rgb(0.6, 0.8, 1.0)
+                } else {
+                    Color::GRAY
+                }
+            };
+            let mut rows = Vec::new();
+            for action in Action::ALL {
+                let labels: Vec<String> = bindings
+                    .get(action)
+                    .iter()
+                    .map(|binding| binding.label())
+                    .collect();
+                let labels = if labels.is_empty() {
+                    "(unbound)".to_string()
+                } else {
+                    labels.join(", ")
+                };
+                rows.push(format!("{}: {}", action.description(), labels));
+            }
+            rows.push(format!(
+                "Moves remembered during an animation: {}",
+                buffer_settings.depth
+            ));
+            rows.push(format!(
+                "Forget remembered moves on undo: {}",
+                if buffer_settings.clear_on_undo {
+                    "yes"
+                } else {
+                    "no"
+                }
+            ));
+
+            for (index, row) in rows.into_iter().enumerate() {
+                list.spawn(TextBundle::from_section(
+                    row,
+                    TextStyle {
+                        font_size: 12.0,
+                        color: row_color(index),
+                        ..default()
+                    },
+                ));
+            }
+        });
+}
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Settings::default())
+            .add_systems(
+                Update,
+                open_settings.run_if(in_state(GameState::LevelSelect)),
+            )
+            .add_systems(OnEnter(GameState::Settings), show_settings)
+            .add_systems(
+                Update,
+                (
+                    edit_bindings.before(bevy::window::close_on_esc),
+                    draw_settings.after(edit_bindings),
+                )
+                    .run_if(in_state(GameState::Settings)),
+            );
+    }
+}
@@ -0,0 +1,97 @@
+use std::{fs, io, path::PathBuf};
+
+use bevy::prelude::*;
+
+use bevy_sokoban::storage::data_directory;
+
+use crate::{
+    action_plugin::{Action, Actions},
+    play_plugin::{ActiveCollection, LevelCompleteEvent},
+    GameState,
+};
+
+pub struct SolutionPlugin;
+
+#[derive(Resource, Default)]
+pub struct LastSolution {
+    pub level: i32,
+    pub lurd: String,
+}
+
+#[derive(Component)]
+struct SolutionText;
+
+pub fn solution_path(collection_name: &str, level: i32) -> PathBuf {
+    data_directory()
+        .join("solutions")
+        .join(format!("{}-{}.lurd", collection_name, level))
+}
+
+fn export_to_file(collection_name: &str, last_solution: &LastSolution) -> io::Result<PathBuf> {
+    let path = solution_path(collection_name, last_solution.level);
+    if let Some(directory) = path.parent() {
+        fs::create_dir_all(directory)?;
+    }
+    fs::write(&path, format!("{}\n", last_solution.lurd))?;
+    Ok(path)
+}
+
+fn remember_solution(
+    mut last_solution: ResMut<LastSolution>,
+    mut level_complete_reader: EventReader<LevelCompleteEvent>,
+) {
+    for level_complete in level_complete_reader.read() {
+        last_solution.level = level_complete.level;
+        last_solution.lurd = level_complete.lurd.clone();
+    }
+}
+
+fn export_solution(
+    mut commands: Commands,
+    actions: Res<Actions>,
+    active_collection: Res<ActiveCollection>,
+    last_solution: Res<LastSolution>,
+    text_query: Query<Entity, With<SolutionText>>,
+) {
+    if !actions.just_pressed(Action::ExportSolution) || last_solution.lurd.is_empty() {
+        return;
+    }
+
+    let message = match export_to_file(&active_collection.name, &last_solution) {
+        Ok(path) => format!(
+            "Exported the solution of level {} to {}",
+            last_solution.level,
+            path.display()
+        ),
+        Err(error) => format!("Could not export the solution: {}", error),
+    };
+
+    for entity in text_query.iter() {
+        commands.entity(entity).despawn();
+    }
+    commands.spawn((
+        SolutionText,
+        TextBundle::from_section(
+            message,
+            TextStyle {
+                font_size: 14.0,
+                color: Color::WHITE,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(25.0),
+            left: Val::Px(5.0),
+            ..default()
+        }),
+    ));
+}
+
+impl Plugin for SolutionPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(LastSolution::default())
+            .add_systems(Update, remember_solution)
+            .add_systems(Update, export_solution.run_if(in_state(GameState::Playing)));
+    }
+}
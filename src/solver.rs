@@ -0,0 +1,521 @@
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap, HashSet, VecDeque},
+    fmt,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    cell::{Cell, Layout},
+    get_enclosed_floor_positions, Direction, Position,
+};
+
+const UNREACHABLE: u32 = u32::MAX;
+
+#[derive(Clone)]
+pub struct Puzzle {
+    pub walls: HashSet<Position>,
+    pub goals: HashSet<Position>,
+    pub blocks: HashSet<Position>,
+    pub player: Position,
+}
+
+impl Puzzle {
+    pub fn from_layout(layout: &Layout) -> Option<Puzzle> {
+        let mut walls = HashSet::new();
+        let mut goals = HashSet::new();
+        let mut blocks = HashSet::new();
+        let mut player = None;
+
+        for (row_index, row) in layout.iter().enumerate() {
+            for (col_index, cell) in row.iter().enumerate() {
+                let position = Position {
+                    x: col_index as i32,
+                    y: row_index as i32,
+                };
+
+                if cell.contains(Cell::WALL) {
+                    walls.insert(position);
+                }
+                if cell.contains(Cell::GOAL) {
+                    goals.insert(position);
+                }
+                if cell.contains(Cell::BLOCK) {
+                    blocks.insert(position);
+                }
+                if cell.contains(Cell::PLAYER) {
+                    player = Some(position);
+                }
+            }
+        }
+
+        Some(Puzzle {
+            walls,
+            goals,
+            blocks,
+            player: player?,
+        })
+    }
+
+    pub fn floor_positions(&self) -> Option<Vec<Position>> {
+        let width = self.walls.iter().map(|wall| wall.x + 1).max().unwrap_or(0);
+        let height = self.walls.iter().map(|wall| wall.y + 1).max().unwrap_or(0);
+        get_enclosed_floor_positions(self.player, &self.walls, width, height)
+    }
+
+    pub fn first_push(&self, moves: &[Direction]) -> Option<(Position, Direction)> {
+        let mut player = self.player;
+        for direction in moves {
+            player = player.step(*direction);
+            if self.blocks.contains(&player) {
+                return Some((player, *direction));
+            }
+        }
+        None
+    }
+}
+
+#[derive(Clone)]
+pub struct SolverConfig {
+    pub max_nodes: usize,
+    pub time_limit: Option<Duration>,
+}
+
+impl Default for SolverConfig {
+    fn default() -> Self {
+        Self {
+            max_nodes: 500_000,
+            time_limit: Some(Duration::from_secs(10)),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Solution {
+    pub moves: Vec<Direction>,
+    pub pushes: usize,
+    pub nodes: usize,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SolveError {
+    Unsolvable,
+    BudgetExceeded { nodes: usize },
+}
+
+impl fmt::Display for SolveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SolveError::Unsolvable => write!(f, "level has no solution"),
+            SolveError::BudgetExceeded { nodes } => {
+                write!(f, "gave up after searching {} positions", nodes)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SolveError {}
+
+// Cells the player can never reach are treated as walls.
+struct Grid {
+    width: usize,
+    origin: Position,
+    floor: Vec<bool>,
+    goals: Vec<usize>,
+    // For every goal, the fewest pushes needed to bring a block from each cell onto it.
+    goal_distances: Vec<Vec<u32>>,
+}
+
+impl Grid {
+    fn new(puzzle: &Puzzle) -> Grid {
+        let positions = puzzle
+            .walls
+            .iter()
+            .chain(puzzle.goals.iter())
+            .chain(puzzle.blocks.iter())
+            .chain(std::iter::once(&puzzle.player));
+        let min_x = positions.clone().map(|p| p.x).min().unwrap() - 1;
+        let max_x = positions.clone().map(|p| p.x).max().unwrap() + 1;
+        let min_y = positions.clone().map(|p| p.y).min().unwrap() - 1;
+        let max_y = positions.map(|p| p.y).max().unwrap() + 1;
+
+        let width = (max_x - min_x + 1) as usize;
+        let height = (max_y - min_y + 1) as usize;
+        let mut grid = Grid {
+            width,
+            origin: Position { x: min_x, y: min_y },
+            floor: vec![false; width * height],
+            goals: Vec::new(),
+            goal_distances: Vec::new(),
+        };
+
+        let mut to_visit = vec![grid.index(puzzle.player)];
+        while let Some(cell) = to_visit.pop() {
+            let position = grid.position(cell);
+            let on_edge = position.x == min_x
+                || position.x == max_x
+                || position.y == min_y
+                || position.y == max_y;
+            if grid.floor[cell] || on_edge || puzzle.walls.contains(&position) {
+                continue;
+            }
+            grid.floor[cell] = true;
+            to_visit.extend(Direction::ALL.map(|direction| grid.step(cell, direction)));
+        }
+
+        for goal in &puzzle.goals {
+            let cell = grid.index(*goal);
+            if grid.floor[cell] {
+                grid.goals.push(cell);
+            }
+        }
+        grid.goal_distances = grid
+            .goals
+            .iter()
+            .map(|goal| grid.pull_distances(*goal))
+            .collect();
+        grid
+    }
+
+    fn index(&self, position: Position) -> usize {
+        (position.y - self.origin.y) as usize * self.width + (position.x - self.origin.x) as usize
+    }
+
+    fn position(&self, cell: usize) -> Position {
+        Position {
+            x: self.origin.x + (cell % self.width) as i32,
+            y: self.origin.y + (cell / self.width) as i32,
+        }
+    }
+
+    fn step(&self, cell: usize, direction: Direction) -> usize {
+        match direction {
+            Direction::Up => cell - self.width,
+            Direction::Down => cell + self.width,
+            Direction::Left => cell - 1,
+            Direction::Right => cell + 1,
+        }
+    }
+
+    // Pulling a block back from a goal finds every cell it could be pushed onto the goal from.
+    fn pull_distances(&self, goal: usize) -> Vec<u32> {
+        let mut distances = vec![UNREACHABLE; self.floor.len()];
+        let mut to_visit = VecDeque::from([goal]);
+        distances[goal] = 0;
+
+        while let Some(cell) = to_visit.pop_front() {
+            for direction in Direction::ALL {
+                // Floor never touches the grid's edge, so only a step from floor stays inside it.
+                let previous = self.step(cell, direction);
+                if !self.floor[previous] || distances[previous] != UNREACHABLE {
+                    continue;
+                }
+                if self.floor[self.step(previous, direction)] {
+                    distances[previous] = distances[cell] + 1;
+                    to_visit.push_back(previous);
+                }
+            }
+        }
+        distances
+    }
+
+    fn is_dead(&self, cell: usize) -> bool {
+        self.goal_distances
+            .iter()
+            .all(|distances| distances[cell] == UNREACHABLE)
+    }
+
+    // A lower bound on the pushes left, or None once too few blocks can still reach a goal.
+    fn lower_bound(&self, blocks: &[usize]) -> Option<u32> {
+        let live_blocks = blocks.iter().filter(|block| !self.is_dead(**block)).count();
+        if live_blocks < self.goals.len() {
+            return None;
+        }
+
+        let mut total = 0;
+        for distances in &self.goal_distances {
+            let closest = blocks.iter().map(|block| distances[*block]).min()?;
+            if closest == UNREACHABLE {
+                return None;
+            }
+            total += closest;
+        }
+        Some(total)
+    }
+
+    fn is_solved(&self, occupied: &[bool]) -> bool {
+        self.goals.iter().all(|goal| occupied[*goal])
+    }
+
+    fn occupancy(&self, blocks: &[usize]) -> Vec<bool> {
+        let mut occupied = vec![false; self.floor.len()];
+        for block in blocks {
+            occupied[*block] = true;
+        }
+        occupied
+    }
+
+    fn reachable(&self, player: usize, occupied: &[bool]) -> Vec<bool> {
+        let mut visited = vec![false; self.floor.len()];
+        let mut to_visit = vec![player];
+        while let Some(cell) = to_visit.pop() {
+            if visited[cell] || !self.floor[cell] || occupied[cell] {
+                continue;
+            }
+            visited[cell] = true;
+            to_visit.extend(Direction::ALL.map(|direction| self.step(cell, direction)));
+        }
+        visited
+    }
+
+    // Player positions that can reach each other are the same state.
+    fn normalize(&self, player: usize, occupied: &[bool]) -> usize {
+        self.reachable(player, occupied)
+            .iter()
+            .position(|visited| *visited)
+            .unwrap_or(player)
+    }
+
+    fn path(&self, from: usize, to: usize, occupied: &[bool]) -> Option<Vec<Direction>> {
+        let mut came_from: HashMap<usize, (usize, Direction)> = HashMap::new();
+        let mut to_visit = VecDeque::from([from]);
+        while let Some(cell) = to_visit.pop_front() {
+            if cell == to {
+                let mut path = Vec::new();
+                let mut current = to;
+                while let Some((previous, direction)) = came_from.get(&current) {
+                    path.push(*direction);
+                    current = *previous;
+                }
+                path.reverse();
+                return Some(path);
+            }
+            for direction in Direction::ALL {
+                let next = self.step(cell, direction);
+                if self.floor[next]
+                    && !occupied[next]
+                    && next != from
+                    && !came_from.contains_key(&next)
+                {
+                    came_from.insert(next, (cell, direction));
+                    to_visit.push_back(next);
+                }
+            }
+        }
+        None
+    }
+}
+
+// Cells a block can never be pushed from onto any goal, however the other blocks are arranged.
+pub fn dead_squares(puzzle: &Puzzle) -> HashSet<Position> {
+    let grid = Grid::new(puzzle);
+    (0..grid.floor.len())
+        .filter(|cell| grid.floor[*cell] && grid.is_dead(*cell))
+        .map(|cell| grid.position(cell))
+        .collect()
+}
+
+struct Node {
+    blocks: Vec<usize>,
+    player: usize,
+    pushes: u32,
+    parent: Option<(usize, usize, Direction)>,
+}
+
+// A* search for the solution with the fewest pushes.
+pub fn solve(puzzle: &Puzzle, config: &SolverConfig) -> Result<Solution, SolveError> {
+    let started_at = Instant::now();
+    let grid = Grid::new(puzzle);
+
+    let uncovered_unreachable_goal = puzzle
+        .goals
+        .iter()
+        .any(|goal| !grid.floor[grid.index(*goal)] && !puzzle.blocks.contains(goal));
+    if uncovered_unreachable_goal {
+        return Err(SolveError::Unsolvable);
+    }
+
+    let mut start_blocks: Vec<usize> = puzzle
+        .blocks
+        .iter()
+        .map(|block| grid.index(*block))
+        .filter(|block| grid.floor[*block])
+        .collect();
+    start_blocks.sort_unstable();
+    let start_player = grid.index(puzzle.player);
+    let Some(start_bound) = grid.lower_bound(&start_blocks) else {
+        return Err(SolveError::Unsolvable);
+    };
+
+    let start_occupied = grid.occupancy(&start_blocks);
+    let mut best_pushes: HashMap<(Vec<usize>, usize), u32> = HashMap::new();
+    best_pushes.insert(
+        (
+            start_blocks.clone(),
+            grid.normalize(start_player, &start_occupied),
+        ),
+        0,
+    );
+    let mut nodes = vec![Node {
+        blocks: start_blocks,
+        player: start_player,
+        pushes: 0,
+        parent: None,
+    }];
+    let mut open = BinaryHeap::from([Reverse((start_bound, start_bound, 0))]);
+    let mut expanded = 0;
+
+    while let Some(Reverse((_, _, node_index))) = open.pop() {
+        let occupied = grid.occupancy(&nodes[node_index].blocks);
+        if grid.is_solved(&occupied) {
+            return Ok(build_solution(&grid, &nodes, node_index, expanded));
+        }
+
+        let key = (
+            nodes[node_index].blocks.clone(),
+            grid.normalize(nodes[node_index].player, &occupied),
+        );
+        if best_pushes.get(&key) != Some(&nodes[node_index].pushes) {
+            continue;
+        }
+
+        expanded += 1;
+        if expanded > config.max_nodes
+            || config
+                .time_limit
+                .is_some_and(|limit| started_at.elapsed() > limit)
+        {
+            return Err(SolveError::BudgetExceeded { nodes: expanded });
+        }
+
+        let current_blocks = nodes[node_index].blocks.clone();
+        let reachable = grid.reachable(nodes[node_index].player, &occupied);
+        for (block_index, block) in current_blocks.iter().enumerate() {
+            for direction in Direction::ALL {
+                let behind = grid.step(*block, direction.opposite());
+                let ahead = grid.step(*block, direction);
+                if !reachable[behind] || !grid.floor[ahead] || occupied[ahead] {
+                    continue;
+                }
+
+                let mut blocks = current_blocks.clone();
+                blocks[block_index] = ahead;
+                blocks.sort_unstable();
+                let Some(bound) = grid.lower_bound(&blocks) else {
+                    continue;
+                };
+
+                let pushes = nodes[node_index].pushes + 1;
+                let key = (
+                    blocks.clone(),
+                    grid.normalize(*block, &grid.occupancy(&blocks)),
+                );
+                if best_pushes.get(&key).is_some_and(|best| *best <= pushes) {
+                    continue;
+                }
+                best_pushes.insert(key, pushes);
+
+                open.push(Reverse((pushes + bound, bound, nodes.len())));
+                nodes.push(Node {
+                    blocks,
+                    player: *block,
+                    pushes,
+                    parent: Some((node_index, *block, direction)),
+                });
+            }
+        }
+    }
+
+    Err(SolveError::Unsolvable)
+}
+
+fn build_solution(grid: &Grid, nodes: &[Node], last_node: usize, expanded: usize) -> Solution {
+    let mut pushes = Vec::new();
+    let mut current = last_node;
+    while let Some((parent, block, direction)) = nodes[current].parent {
+        pushes.push((parent, block, direction));
+        current = parent;
+    }
+    pushes.reverse();
+
+    let mut moves = Vec::new();
+    let mut player = nodes[0].player;
+    for (parent, block, direction) in &pushes {
+        let occupied = grid.occupancy(&nodes[*parent].blocks);
+        let push_from = grid.step(*block, direction.opposite());
+        let walk = grid
+            .path(player, push_from, &occupied)
+            .expect("the pushing side was reachable when the push was found");
+        moves.extend(walk);
+        moves.push(*direction);
+        player = *block;
+    }
+
+    Solution {
+        moves,
+        pushes: pushes.len(),
+        nodes: expanded,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        fixtures::puzzle,
+        game::{Game, MoveResult},
+    };
+
+    #[test]
+    fn solves_with_the_fewest_pushes() {
+        let puzzle = puzzle(&["######", "#.   #", "#  $ #", "#  @ #", "######"]);
+        let solution = solve(&puzzle, &SolverConfig::default()).unwrap();
+        assert_eq!(solution.pushes, 3);
+
+        let mut game = Game::new(puzzle.clone());
+        for direction in &solution.moves {
+            game.try_move(*direction);
+        }
+        let lurd = game.lurd().to_string();
+        assert_eq!(lurd.len(), solution.moves.len());
+        assert_eq!(lurd.chars().filter(char::is_ascii_uppercase).count(), 3);
+
+        let mut replay = Game::new(puzzle);
+        for (direction, is_push) in lurd.chars().filter_map(Direction::from_lurd) {
+            let expected = if is_push {
+                MoveResult::Pushed
+            } else {
+                MoveResult::Moved
+            };
+            assert_eq!(replay.try_move(direction), expected);
+        }
+        assert!(replay.is_won());
+    }
+
+    #[test]
+    fn finds_no_solution_for_a_block_stuck_in_a_corner() {
+        let puzzle = puzzle(&["#####", "#$ .#", "# @ #", "#####"]);
+        let config = SolverConfig {
+            max_nodes: 1_000,
+            time_limit: Some(Duration::from_secs(1)),
+        };
+        assert_eq!(solve(&puzzle, &config).unwrap_err(), SolveError::Unsolvable);
+    }
+
+    #[test]
+    fn pulls_from_a_goal_beside_a_gap_stay_on_the_grid() {
+        let puzzle = puzzle(&["#. ##", "# $@#", "#####"]);
+        assert!(!dead_squares(&puzzle).contains(&Position { x: 1, y: 0 }));
+    }
+
+    #[test]
+    fn marks_cells_no_block_can_leave_for_a_goal_as_dead() {
+        let puzzle = puzzle(&["######", "#.   #", "#    #", "#   @#", "######"]);
+        let dead = dead_squares(&puzzle);
+        let expected: HashSet<Position> = [(4, 1), (4, 2), (1, 3), (2, 3), (3, 3), (4, 3)]
+            .into_iter()
+            .map(|(x, y)| Position { x, y })
+            .collect();
+        assert_eq!(dead, expected);
+    }
+}
@@ -0,0 +1,18 @@
+use std::{env, path::PathBuf};
+
+// Follows each platform's convention for application data.
+pub fn data_directory() -> PathBuf {
+    let base_directory = if cfg!(target_os = "windows") {
+        env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        env::var_os("HOME").map(|home| PathBuf::from(home).join("Library/Application Support"))
+    } else {
+        env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")))
+    };
+
+    base_directory
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("bevy-sokoban")
+}
@@ -1,17 +1,60 @@
 use bevy::{prelude::*, sprite::Anchor};
 
-use crate::Position;
+use bevy_sokoban::Position;
+
+pub const TILE_SIZE: f32 = 16.0;
+
+pub trait Translation {
+    fn from_translation(translation: Vec3) -> Self;
+    fn to_translation(self) -> Vec3;
+    fn to_translation_z(self, z: f32) -> Vec3;
+}
+
+impl Translation for Position {
+    fn from_translation(translation: Vec3) -> Position {
+        Position {
+            x: (translation.x / TILE_SIZE).floor() as i32,
+            y: (-translation.y / TILE_SIZE).floor() as i32,
+        }
+    }
+
+    fn to_translation(self) -> Vec3 {
+        self.to_translation_z(1.0)
+    }
+
+    fn to_translation_z(self, z: f32) -> Vec3 {
+        Vec3::new(self.x as f32 * TILE_SIZE, self.y as f32 * -TILE_SIZE, z)
+    }
+}
 
 pub fn spawn_floor(asset_server: &Res<AssetServer>, position: Position) -> SpriteBundle {
-    let floor_translation = position.to_translation_z(0.0);
+    spawn_tile(asset_server, "floor.png", position.to_translation_z(0.0))
+}
+
+pub fn spawn_wall(asset_server: &Res<AssetServer>, position: Position) -> SpriteBundle {
+    spawn_tile(asset_server, "wall.png", position.to_translation())
+}
+
+pub fn spawn_goal(asset_server: &Res<AssetServer>, position: Position) -> SpriteBundle {
+    spawn_tile(asset_server, "goal.png", position.to_translation_z(0.5))
+}
+
+pub fn spawn_block(asset_server: &Res<AssetServer>, position: Position) -> SpriteBundle {
+    spawn_tile(asset_server, "block.png", position.to_translation())
+}
+
+pub fn spawn_player(asset_server: &Res<AssetServer>, position: Position) -> SpriteBundle {
+    spawn_tile(asset_server, "player.png", position.to_translation())
+}
 
+fn spawn_tile(asset_server: &Res<AssetServer>, texture: &str, translation: Vec3) -> SpriteBundle {
     SpriteBundle {
         sprite: Sprite {
             anchor: Anchor::TopLeft,
             ..default()
         },
-        texture: asset_server.load("floor.png"),
-        transform: Transform::from_translation(floor_translation),
+        texture: asset_server.load(texture.to_string()),
+        transform: Transform::from_translation(translation),
         ..default()
     }
 }